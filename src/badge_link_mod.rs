@@ -0,0 +1,157 @@
+// badge_link_mod.rs
+//! Finds the links for the shield badges.
+//!
+//! The link is resolved in this order: CLI argument, git remote,
+//! `package.repository`, `package.homepage` and `workspace.package.repository` from Cargo.toml.
+
+use crate::count_lines_mod::CargoToml;
+use crate::AppObject;
+use crate::TraitCountLines;
+
+use std::path::Path;
+
+/// The placeholder in a badge link that is replaced with the resolved common link.
+pub const LINK_PLACEHOLDER: &str = "{link}";
+
+/// The names of the badges in the order of the output.
+pub const BADGE_NAMES: [&str; 5] = ["code", "doc_comments", "comments", "examples", "tests"];
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Links for every badge.
+/// An empty string means the common link.
+/// The placeholder {link} is replaced with the common link,
+/// so `{link}tree/master/tests` links the tests badge to the tests folder.
+pub struct BadgeLinks {
+    pub src_code: String,
+    pub doc_comments: String,
+    pub comments: String,
+    pub examples: String,
+    pub tests: String,
+}
+
+impl BadgeLinks {
+    /// All badges have the same link.
+    pub fn same(link: &str) -> BadgeLinks {
+        BadgeLinks {
+            src_code: link.to_string(),
+            doc_comments: link.to_string(),
+            comments: link.to_string(),
+            examples: link.to_string(),
+            tests: link.to_string(),
+        }
+    }
+    /// Sets the link for one badge by name: code, doc_comments, comments, examples or tests.
+    pub fn set(&mut self, badge_name: &str, link: &str) -> anyhow::Result<()> {
+        let field = match badge_name {
+            "code" => &mut self.src_code,
+            "doc_comments" => &mut self.doc_comments,
+            "comments" => &mut self.comments,
+            "examples" => &mut self.examples,
+            "tests" => &mut self.tests,
+            _ => anyhow::bail!(
+                "Error: unknown badge {}. Use: code, doc_comments, comments, examples or tests.",
+                badge_name
+            ),
+        };
+        *field = link.to_string();
+        Ok(())
+    }
+    /// Parses the CLI argument in the form `badge_name=link`.
+    pub fn set_from_arg(&mut self, arg: &str) -> anyhow::Result<()> {
+        let (badge_name, link) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Error: badge link {} is not badge_name=link", arg))?;
+        self.set(badge_name.trim(), link.trim())
+    }
+    /// Returns the links with empty strings and placeholders replaced by the common link.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let mut links = BadgeLinks::default();
+    /// links.set("tests", "{link}tree/master/tests").unwrap();
+    /// let links = links.resolve("https://github.com/user/repo/");
+    /// assert_eq!(links.src_code, "https://github.com/user/repo/");
+    /// assert_eq!(links.tests, "https://github.com/user/repo/tree/master/tests");
+    /// ```
+    pub fn resolve(&self, link: &str) -> BadgeLinks {
+        let resolve_one = |badge_link: &str| {
+            if badge_link.is_empty() {
+                link.to_string()
+            } else {
+                badge_link.replace(LINK_PLACEHOLDER, link)
+            }
+        };
+        BadgeLinks {
+            src_code: resolve_one(&self.src_code),
+            doc_comments: resolve_one(&self.doc_comments),
+            comments: resolve_one(&self.comments),
+            examples: resolve_one(&self.examples),
+            tests: resolve_one(&self.tests),
+        }
+    }
+}
+
+impl AppObject {
+    /// Returns the common link for the badges.
+    /// The order is: the link argument, git remote, then the Cargo.toml fallback.
+    /// Returns empty string if nothing is found.
+    pub fn resolve_link(&self, link: &str) -> String {
+        if !link.is_empty() {
+            return link.to_string();
+        }
+        let link = self.process_git_remote();
+        if !link.is_empty() {
+            return link;
+        }
+        match self.file_system.read_to_string(Path::new("Cargo.toml")) {
+            Ok(cargo_toml) => link_from_cargo_toml(&cargo_toml).unwrap_or_default(),
+            Err(e) => {
+                println!("{}", e);
+                "".to_string()
+            }
+        }
+    }
+}
+
+/// Returns the link from the Cargo.toml content in this order:
+/// `package.repository`, `package.homepage`, `workspace.package.repository`.
+/// The link always ends with /, like the link from git remote.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let link = link_from_cargo_toml(r#"
+/// [package]
+/// name = "x"
+/// homepage = "https://example.com/x"
+/// "#);
+/// assert_eq!(link.unwrap(), "https://example.com/x/");
+/// ```
+pub fn link_from_cargo_toml(cargo_toml: &str) -> Option<String> {
+    let cargo_toml: CargoToml = match toml::from_str(cargo_toml) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("{}", e);
+            return None;
+        }
+    };
+    // inherited fields `{ workspace = true }` are not strings and are skipped
+    let as_string = |value: Option<toml::Value>| value.and_then(|v| v.as_str().map(String::from));
+    let (repository, homepage) = match cargo_toml.package {
+        Some(package) => (as_string(package.repository), as_string(package.homepage)),
+        None => (None, None),
+    };
+    let workspace_repository = cargo_toml
+        .workspace
+        .and_then(|workspace| workspace.package)
+        .and_then(|package| package.repository);
+    repository
+        .into_iter()
+        .chain(homepage)
+        .chain(workspace_repository)
+        .find(|link| !link.trim().is_empty())
+        .map(|link| format!("{}/", link.trim().trim_end_matches('/')))
+}
@@ -0,0 +1,175 @@
+// baseline_mod.rs
+//! Baseline snapshot and regression detection for CI.
+//!
+//! `--save-baseline loc-baseline.json` writes the lines of the workspace, every member and every file.
+//! `--compare-baseline loc-baseline.json` counts again and lists the metrics that dropped
+//! by more than the allowed percentage, like tests lines or doc comments.
+//! Regressions of the workspace and of the members fail, the files are listed as details.
+
+use crate::count_lines_mod::*;
+use crate::policy_mod::{metric_value, PolicyScope};
+use crate::AppObject;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The metrics compared by default and the allowed drop in percent: tests lines and doc comments must not drop.
+pub fn default_max_drop() -> BTreeMap<String, f64> {
+    let mut max_drop = BTreeMap::new();
+    max_drop.insert("tests_lines".to_string(), 0.0);
+    max_drop.insert("src_doc_comment_lines".to_string(), 0.0);
+    // return
+    max_drop
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// One metric that dropped more than allowed.
+pub struct Regression {
+    pub scope: PolicyScope,
+    /// workspace, the member name or the file path
+    pub target: String,
+    pub metric: String,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+impl Regression {
+    /// The drop in percent of the baseline.
+    pub fn drop_percent(&self) -> f64 {
+        drop_percent(self.baseline, self.current)
+    }
+}
+
+fn drop_percent(baseline: f64, current: f64) -> f64 {
+    if baseline <= 0.0 {
+        0.0
+    } else {
+        (baseline - current) / baseline * 100.0
+    }
+}
+
+/// Compares the current report with the baseline.
+/// Returns the metrics that dropped by more than the allowed percentage.
+/// A member or file that is missing now counts as 0 lines.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let baseline = CountReport { total: LinesOfCode::new(100, 20, 0, 50, 0), ..Default::default() };
+/// let current = CountReport { total: LinesOfCode::new(120, 19, 0, 50, 0), ..Default::default() };
+/// let mut max_drop = std::collections::BTreeMap::new();
+/// max_drop.insert("src_doc_comment_lines".to_string(), 10.0);
+/// assert!(compare_reports(&baseline, &current, &max_drop).unwrap().is_empty());
+/// max_drop.insert("src_doc_comment_lines".to_string(), 1.0);
+/// assert_eq!(compare_reports(&baseline, &current, &max_drop).unwrap().len(), 1);
+/// ```
+pub fn compare_reports(
+    baseline: &CountReport,
+    current: &CountReport,
+    max_drop: &BTreeMap<String, f64>,
+) -> anyhow::Result<Vec<Regression>> {
+    let mut targets: Vec<(PolicyScope, String, &LinesOfCode, LinesOfCode)> = vec![(
+        PolicyScope::Workspace,
+        "workspace".to_string(),
+        &baseline.total,
+        current.total.clone(),
+    )];
+    for m in baseline.members.iter() {
+        let now = current
+            .members
+            .iter()
+            .find(|c| c.member == m.member)
+            .map(|c| c.lines_of_code.clone())
+            .unwrap_or_default();
+        targets.push((PolicyScope::Member, m.member.clone(), &m.lines_of_code, now));
+    }
+    for f in baseline.files.iter() {
+        let now = current
+            .files
+            .iter()
+            .find(|c| c.path == f.path)
+            .map(|c| c.lines_of_code.clone())
+            .unwrap_or_default();
+        targets.push((PolicyScope::File, f.path.clone(), &f.lines_of_code, now));
+    }
+    let mut regressions = vec![];
+    for (scope, target, before, now) in targets {
+        for (metric, allowed) in max_drop.iter() {
+            let baseline = metric_value(before, metric)?;
+            let current = metric_value(&now, metric)?;
+            if drop_percent(baseline, current) > *allowed {
+                regressions.push(Regression {
+                    scope,
+                    target: target.clone(),
+                    metric: metric.clone(),
+                    baseline,
+                    current,
+                });
+            }
+        }
+    }
+    // return
+    Ok(regressions)
+}
+
+impl AppObject {
+    /// Counts the lines and writes the report as JSON.
+    pub fn save_baseline(&self, path: &str) -> anyhow::Result<CountReport> {
+        let report = self.count_report()?;
+        self.file_system.write(
+            Path::new(path),
+            serde_json::to_string_pretty(&report)?.as_bytes(),
+        )?;
+        // return
+        Ok(report)
+    }
+    /// Counts the lines and compares them with the baseline file.
+    pub fn compare_baseline(&self, path: &str) -> anyhow::Result<Vec<Regression>> {
+        let baseline = self
+            .file_system
+            .read_to_string(Path::new(path))
+            .map_err(|e| anyhow::anyhow!("Error: baseline {}: {}", path, e))?;
+        let baseline: CountReport = serde_json::from_str(&baseline)
+            .map_err(|e| anyhow::anyhow!("Error: baseline {}: {}", path, e))?;
+        let current = self.count_report()?;
+        // the user values replace the default only for their metrics
+        let mut max_drop = default_max_drop();
+        max_drop.extend(self.baseline_max_drop.clone());
+        // return
+        compare_reports(&baseline, &current, &max_drop)
+    }
+}
+
+/// Returns the markdown table of the regressions.
+pub fn regressions_to_md_table(regressions: &[Regression]) -> String {
+    let mut table = String::from(
+        "
+| scope | crate or file | metric | baseline | current | drop |
+| :---- | :------------ | :----- | -------: | ------: | ---: |
+",
+    );
+    for r in regressions.iter() {
+        let scope = match r.scope {
+            PolicyScope::Workspace => "workspace",
+            PolicyScope::Member => "member",
+            PolicyScope::File => "file",
+        };
+        let target = if r.target.is_empty() {
+            "project"
+        } else {
+            &r.target
+        };
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {:.1}% |\n",
+            scope,
+            target,
+            r.metric,
+            (r.baseline * 100.0).round() / 100.0,
+            (r.current * 100.0).round() / 100.0,
+            r.drop_percent()
+        ));
+    }
+    // return
+    table
+}
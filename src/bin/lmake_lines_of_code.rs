@@ -1,14 +1,14 @@
 // region: lmake_md_to_doc_comments include README.md A //!
 //! # lmake_lines_of_code  
 //!
-//! ***version: 1.1.7  date: 2020-08-22 authors: Luciano Bestia***  
+//! ***version: 2.0.0  date: 2026-10-19 authors: Luciano Bestia***  
 //! **Lines of code for Rust projects**
 //!
-//! [![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-379-green.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
-//! [![Lines in Doc comments](https://img.shields.io/badge/Lines_in_Doc_comments-269-blue.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
-//! [![Lines in Comments](https://img.shields.io/badge/Lines_in_comments-56-purple.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-5113-green.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in Doc comments](https://img.shields.io/badge/Lines_in_Doc_comments-1840-blue.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in Comments](https://img.shields.io/badge/Lines_in_comments-202-purple.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
 //! [![Lines in examples](https://img.shields.io/badge/Lines_in_examples-0-yellow.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
-//! [![Lines in tests](https://img.shields.io/badge/Lines_in_tests-142-orange.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in tests](https://img.shields.io/badge/Lines_in_tests-1390-orange.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
 //!
 //! [![crates.io](https://meritbadge.herokuapp.com/lmake_lines_of_code)](https://crates.io/crates/lmake_lines_of_code) [![Documentation](https://docs.rs/lmake_lines_of_code/badge.svg)](https://docs.rs/lmake_lines_of_code/) [![crev reviews](https://web.crev.dev/rust-reviews/badge/crev_count/lmake_lines_of_code.svg)](https://web.crev.dev/rust-reviews/crate/lmake_lines_of_code/) [![Lib.rs](https://img.shields.io/badge/Lib.rs-rust-orange.svg)](https://lib.rs/crates/lmake_lines_of_code/) [![Licence](https://img.shields.io/badge/license-MIT-blue.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/blob/master/LICENSE) [![Rust](https://github.com/LucianoBestia/lmake_lines_of_code/workflows/RustAction/badge.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
 //!
@@ -46,10 +46,58 @@
 //!
 //! All other files: md, toml, html, js, ... are not counted.  
 //!
+//! The files are read as bytes. The line ends can be LF, CRLF or a lone CR. The UTF-8 BOM at the start of the file is ignored.\
+//! Files with invalid UTF-8 are counted with a lossy decode and reported as warnings.  
+//! Symlinks are followed. The option `--no-follow-symlinks` skips them. Every folder is visited only once,\
+//! so a symlink to a parent folder does not cause an infinite loop. File names that are not UTF-8 are supported.  
+//!
 //! ### Workspace
 //!
 //! Workspaces have member projects, that are written in cargo.toml.\
 //! The program counts lines of every project and sums them together.  
+//! The files of all the projects are read and classified in parallel with a pool of threads, one thread per CPU.\
+//! The option `--jobs` limits the number of threads. The result does not depend on the order of the threads:  
+//! `lmake_lines_of_code --jobs 4`  
+//! The count of every file is cached in `target/lmake_lines_of_code/cache.json` by path, size, mtime and content hash.\
+//! Only the changed files are classified again. A new version of the tool or of the classification rules drops the cache. The option `--no-cache` counts all the files without the cache:  
+//! `lmake_lines_of_code --no-cache`  
+//!
+//! ### Ignored and untracked files
+//!
+//! By default all the rs files are counted, only some big folders like `target` are skipped.\
+//! The option `--respect-ignore` skips the files that match the rules in `.gitignore`, `.ignore` and `.git/info/exclude`:  
+//! `lmake_lines_of_code --respect-ignore`  
+//! The option `--tracked` counts only the files tracked by git (`git ls-files`):  
+//! `lmake_lines_of_code --tracked`  
+//! If git cannot list the tracked files, `--tracked` is an error instead of counting nothing.  
+//!
+//! ### Include and exclude patterns
+//!
+//! The glob patterns `--include` and `--exclude` are matched against the path relative to the project root:  
+//! `lmake_lines_of_code --exclude "src/generated/**" --exclude "**/*_pb.rs"`  
+//! Without include patterns all files are included. The exclude patterns win over the include patterns.\
+//! The same patterns can be written in Cargo.toml or in the config file (see [Configuration](#configuration)):  
+//!
+//! ```toml
+//! [package.metadata.lmake_lines_of_code]
+//! include = ["src/**", "tests/**"]
+//! exclude = ["src/old/**"]
+//! generated = ["src/proto/**"]
+//! ```
+//!
+//! The option `--list-files` shows every counted file and its kind: src, tests or examples:  
+//! `lmake_lines_of_code --list-files`  
+//!
+//! ### Generated code
+//!
+//! Generated code is counted in `generated_lines` and not as hand-written code. A file is generated if:  
+//!
+//! 1. the comments at the start of the file, before the first line of code, contain `@generated` or `automatically generated by` (prost, tonic, bindgen),  
+//! 2. it matches a `--generated` glob pattern or the `generated` list in the Cargo.toml config,  
+//! 3. it is the target of `include!("file.rs")` in another counted file.  
+//!
+//! Sources included with `include!(concat!(env!("OUT_DIR"), ...))` are in the target folder and are never counted.  
+//! The markdown table and the subcommands show the generated lines in their own column.  
 //!
 //! ## Output
 //!
@@ -57,10 +105,133 @@
 //! If the CLI is called with one argument:  
 //! `lmake_lines_of_code http://website`  
 //! This will be used for the link of all 4 shield badges.  
-//! Else the app will try  
+//! Else the app will read the remote url from the `.git/config` file (also in worktrees), so the git binary is not required.\
+//! If that fails, it will try  
 //! `git remote -v`  
 //! to get the remote url.  
+//! The remote is chosen in this order: `upstream`, `origin`, the first remote.\
+//! Use the option `--remote` to choose another remote:  
+//! `lmake_lines_of_code --remote my_fork`  
+//! If there is no git remote, the link is taken from Cargo.toml in this order: `package.repository`, `package.homepage`, `workspace.package.repository`.  
+//! Every badge can have a different link. The placeholder `{link}` is replaced with the common link:  
+//! `lmake_lines_of_code --badge-link tests={link}tree/master/tests --badge-link examples={link}tree/master/examples`  
+//! The badge names are: code, doc_comments, comments, examples and tests.  
 //! You can copy/paste it into README.md.  
+//! The option `--categories code,tests` shows only some badges and `--badge-style flat-square` sets the shields.io style.\
+//! The option `--output-format json` prints the counts as JSON instead of the markdown table and badges.  
+//!
+//! ### Ratios
+//!
+//! The ratios show the quality at a glance. They are printed in the markdown table under the lines:  
+//!
+//! 1. `doc_comments_per_code`: doc comment lines per code line,  
+//! 2. `comment_density`: doc comments and comments in all the lines of src,  
+//! 3. `tests_to_code`: tests lines per code line,  
+//! 4. `examples_to_code`: examples lines per code line.  
+//!
+//! The option `--ratio-badges tests_to_code,doc_comments_per_code` adds shield badges for the ratios.\
+//! The badge is red below the yellow threshold, yellow below the green threshold and green above.\
+//! The thresholds can be changed for every ratio: `--ratio-threshold tests_to_code=0.3:0.8` or in the config file:  
+//!
+//! ```toml
+//! ratio_badges = ["tests_to_code", "doc_comments_per_code"]
+//! [ratio_thresholds]
+//! tests_to_code = { yellow = 0.3, green = 0.8 }
+//! ```
+//!
+//! ### Numbers
+//!
+//! The option `--number-format` writes the numbers in the badges, the markdown table and the trend charts:  
+//!
+//! 1. `plain`: `123456`, the default,  
+//! 2. `separated`: `123,456`. The separator can be changed with `--thousands-separator`, like `--thousands-separator .`,  
+//! 3. `compact`: `123k`, `12.3k`, `1.2M`. Below 100 units the number has 1 decimal, `--number-decimals 2` writes `1.23M`.  
+//!
+//! The JSON output, the baseline and the history log always have the raw numbers.  
+//!
+//! ## Configuration
+//!
+//! Every setting can come from the CLI flags, the `LMAKE_LOC_*` env vars, the config files and Cargo.toml.\
+//! The CLI flags win over the env vars, the env vars win over the config files and the config files win over Cargo.toml.\
+//! The include, exclude and generated patterns of all the sources are added together.  
+//! The config files are `.lmake_lines_of_code.toml` in the project root and in all the parent folders. The nearest file wins.\
+//! The file given with `--config` replaces them. In Cargo.toml the settings are in `[workspace.metadata.lmake_lines_of_code]`
+//! and `[package.metadata.lmake_lines_of_code]`, the package wins:  
+//!
+//! ```toml
+//! categories = ["code", "doc_comments", "tests"]
+//! exclude = ["src/generated/**"]
+//! file_filter = "respect-ignore"
+//! output_format = "markdown"
+//! remote = "upstream"
+//! readme = ["README.md", "docs/index.md"]
+//! badge_style = "flat-square"
+//! number_format = "compact"
+//! jobs = 4
+//! no_cache = false
+//! follow_symlinks = true
+//! ```
+//!
+//! The env vars have the same names in uppercase, like `LMAKE_LOC_BADGE_STYLE=flat-square`. Lists are separated by commas. An unknown `LMAKE_LOC_*` var prints a warning.\
+//! `LMAKE_LOC_PROJECT_ROOT` and `--project-root` choose the folder with Cargo.toml. `LMAKE_LOC_CONFIG` chooses the config file.  
+//! Every workspace member can override the workspace settings in its own Cargo.toml or `.lmake_lines_of_code.toml`.\
+//! The member adds its include, exclude and generated patterns. With `categories` the member counts only these categories,
+//! so `categories = ["code", "doc_comments", "comments", "tests"]` leaves its examples out of the examples badge.  
+//!
+//! ## Count at a git revision
+//!
+//! The option `--rev` counts the files at any git commit, tag or branch without checking it out:  
+//! `lmake_lines_of_code --rev v1.1.7`  
+//! The files are read from the git object database with `git ls-tree` and `git cat-file`.\
+//! Uncommitted files in the working tree are never counted. A revision that is not a commit stops with an error before counting.  
+//!
+//! ## History
+//!
+//! The subcommand `history` counts the lines at every tag, sorted by date:  
+//! `lmake_lines_of_code history`  
+//! or at every Nth commit on a branch:  
+//! `lmake_lines_of_code history --branch master --every 10`  
+//! It prints a markdown table and can write the time series as CSV or JSON for charts:  
+//! `lmake_lines_of_code history --csv loc_history.csv --json loc_history.json`  
+//! The counts are cached by commit in `target/lmake_lines_of_code/history_cache.json`, so reruns count only the new revisions.\
+//! Other include, exclude or generated patterns, other member settings or new classification rules drop the cache.  
+//!
+//! ## Diff between revisions
+//!
+//! The subcommand `diff` reports how every category changed between two revisions, for the release notes:  
+//! `lmake_lines_of_code diff v1.0.0..HEAD`  
+//! The added and removed lines are classified with the same rules as the count of lines:\
+//! the include and exclude patterns, the generated files and the member settings apply.\
+//! It prints the added, removed and net lines overall, per workspace member and per file, like `+12 -3 (+9)`,\
+//! and a sentence that can be pasted in the changelog:  
+//! `Lines of code v1.0.0..HEAD: src code grew by 120 lines, tests grew by 300 lines.`  
+//!
+//! ## Churn
+//!
+//! The subcommand `churn` shows the change activity: lines added and deleted per category, per week and per file:  
+//! `lmake_lines_of_code churn --since 2020-08-01`  
+//! The changed lines of every commit are classified like in the `diff` subcommand. Merge commits are skipped.\
+//! All the commits are read from one `git log -p` stream. The workspace members, their settings and the `include!` targets are read at HEAD.\
+//! The files are sorted by churn. Files with churn in the code, but without new tests, are marked with "no test growth".  
+//!
+//! ## Blame
+//!
+//! The subcommand `blame` shows which contributors wrote the code, doc comments, comments and tests currently in the repository:  
+//! `lmake_lines_of_code blame`  
+//! It runs `git blame --line-porcelain` for every counted file and attributes every line to its author.\
+//! The lines are classified like the count: the generated patterns, the `include!` targets and the member settings apply.\
+//! The identities are merged with the `.mailmap` file and by email.  
+//!
+//! ## Watch
+//!
+//! The subcommand `watch` counts the lines, prints the table and then counts again every time a file changes:  
+//! `lmake_lines_of_code watch --readme`  
+//! It polls the src, tests and examples folders of every workspace member and the Cargo.toml.\
+//! The poll reads only the modification times of the counted files and their folders. The tree is listed again only after a change.\
+//! With `--tracked` it also polls the git index, so `git add` is noticed.\
+//! If the files cannot be listed, for example Cargo.toml is invalid, the error is printed and watch keeps waiting for the next change.\
+//! A burst of saves is counted once, after the files are quiet for `--debounce` milliseconds (default 500).\
+//! With `--readme` the shield badges in README.md are updated on every change. Only the changed files are classified again, thanks to the cache.  
 //!
 //! ## Include into README.md
 //!
@@ -70,9 +241,97 @@
 //! 2. `[comment]: # (lmake_lines_of_code end)`  
 //!
 //! the CLI will include the shield badges code between them.  
+//! Use `--readme-target docs/index.md` to include the badges into other markdown files instead of README.md.  
 //! It will erase the previous content.  
+//! If the content does not change, the file is not written.  
 //! Use git diff to see the change.  
 //!
+//! ## Pre-commit hook
+//!
+//! The option `--staged` counts only the lines added and removed in the git index (`git diff --cached`) and prints the deltas for every category:  
+//! `lmake_lines_of_code --staged`  
+//! The subcommand `install-hook` writes the git pre-commit hook:  
+//! `lmake_lines_of_code install-hook`  
+//! Before every commit the hook prints the staged deltas, refreshes the shield badges in README.md and re-stages README.md when the counts change.  
+//! README.md is re-staged only if the badges were the only change in it. If README.md had unstaged edits, the hook stops the commit and you stage README.md yourself.  
+//! The badges count the files in the working tree, unstaged changes included.\
+//! An existing pre-commit hook is not overwritten without `--force`.  
+//!
+//! ## Check policy
+//!
+//! The subcommand `check-policy` is a gate for CI. It counts the lines, prints the violated rules and exits with an error:  
+//! `lmake_lines_of_code check-policy`  
+//! The rules are `[[policy]]` tables in the config file or `[[package.metadata.lmake_lines_of_code.policy]]` in Cargo.toml:  
+//!
+//! ```toml
+//! [[policy]]
+//! metric = "tests_to_code"
+//! min = 0.5
+//!
+//! [[policy]]
+//! metric = "doc_comments_per_code"
+//! per = 100
+//! min = 10
+//!
+//! [[policy]]
+//! metric = "src_code_lines"
+//! max = 800
+//! scope = "file"
+//! ```
+//!
+//! The metric is a field of the lines like `src_code_lines` or `tests_lines`, or a ratio like `tests_to_code`. With `per` the metric is multiplied, like per 100 code lines.\
+//! The scope is `workspace` (the default), `member` for every crate or `file` for every counted file. The list `members` limits the rule to some crates, so it needs the scope `member` or `file`.\
+//! The rules in the Cargo.toml or config file of a workspace member apply only to that member.  
+//!
+//! ## Baseline
+//!
+//! CI can fail when tests lines or doc comments drop compared to the main branch.\
+//! On the main branch save the lines of the workspace, every member and every file:  
+//! `lmake_lines_of_code --save-baseline loc-baseline.json`  
+//! On the pull request compare the lines with the saved baseline:  
+//! `lmake_lines_of_code --compare-baseline loc-baseline.json`  
+//! The regressions of the workspace, the members and the files are listed. The workspace and member regressions fail, the files are the details.\
+//! By default `tests_lines` and `src_doc_comment_lines` must not drop. The allowed drop in percent can be set for any metric:  
+//! `lmake_lines_of_code --compare-baseline loc-baseline.json --max-drop tests_lines=5 --max-drop src_doc_comment_lines=10`  
+//! or in the config file with `[baseline_max_drop]`. A metric that is not set keeps its default.  
+//!
+//! ## Trend
+//!
+//! The history log is opt-in. With `--history-log` or `history_log` in the config every run that counts the working tree
+//! (the default run, `--save-baseline`, `--compare-baseline` and `check-policy`) appends one JSON line
+//! with the UTC timestamp, the git commit and the lines:  
+//! `lmake_lines_of_code --history-log target/loc-history.jsonl`  
+//! The subcommand `trend` reads the log and prints an ASCII chart for every category.\
+//! With `--svg` it also writes an SVG chart with one panel per category, every panel with its own axis:  
+//! `lmake_lines_of_code --history-log target/loc-history.jsonl trend --svg loc-trend.svg`  
+//! Unlike the `history` subcommand, the trend does not walk the git history.  
+//!
+//! ## Library
+//!
+//! The crate can be used as a library. The functions `count_str` and `count_reader` count the lines of code that are not on disk,
+//! like files in a patch or in-memory sources:  
+//!
+//! ```rust
+//! use lmake_lines_of_code::*;
+//! let v = count_str("/// doc\nfn main() {}\n", FileKind::Src);
+//! assert_eq!(v.src_code_lines, 1);
+//! ```
+//!
+//! All the file operations for counting, the cache and README.md go through the `FileSystem` trait.\
+//! The field `AppObject.file_system` is the real file system by default. The `MemoryFileSystem` can hold archives, git trees or test fixtures,
+//! so the whole pipeline runs without touching the disk.  
+//! The CLI and the library share the same settings in `Config`. `Config::load` merges all the sources and `AppObject::builder()` accepts the config:  
+//!
+//! ```rust
+//! use lmake_lines_of_code::*;
+//! let config = Config {
+//!     exclude: vec!["src/generated/**".to_string()],
+//!     categories: vec!["code".to_string(), "tests".to_string()],
+//!     ..Config::default()
+//! };
+//! let app = AppObject::builder().config(config).build().unwrap();
+//! ```
+//!
 //! ## Install and run
 //!
 //! `cargo install lmake_lines_of_code`  
@@ -89,12 +348,14 @@
 use ansi_term::Colour::{Green, Red, Yellow};
 //use ansi_term::Style;
 //use unwrap::unwrap;
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
 // endregion
 
 use lmake_lines_of_code::*;
 
-#[allow(clippy::print_stdout, clippy::integer_arithmetic)]
+#[allow(clippy::print_stdout, clippy::arithmetic_side_effects)]
 /// The program starts here.
 fn main() {
     // this function is different for Windows and for Linux.
@@ -107,16 +368,375 @@ fn main() {
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .arg(clap::Arg::with_name("link").help("Link to include in shield badge. If not defined, the git remote repository will be used."))
+        .arg(clap::Arg::with_name("remote").long("remote").takes_value(true).value_name("name").help("Name of the git remote for the link. If not defined: upstream, origin or the first remote."))
+        .arg(clap::Arg::with_name("badge_link").long("badge-link").takes_value(true).multiple(true).number_of_values(1).value_name("badge=link").help("Link for one badge: code, doc_comments, comments, examples or tests. The {link} placeholder is replaced with the common link."))
+        .arg(clap::Arg::with_name("rev").long("rev").takes_value(true).value_name("commit|tag|branch").help("Count the files at the git revision without checking it out."))
+        .arg(clap::Arg::with_name("respect_ignore").long("respect-ignore").help("Skip the files ignored by .gitignore, .ignore and .git/info/exclude."))
+        .arg(clap::Arg::with_name("tracked").long("tracked").conflicts_with("respect_ignore").help("Count only the files tracked by git (git ls-files)."))
+        .arg(clap::Arg::with_name("include").long("include").takes_value(true).multiple(true).number_of_values(1).value_name("glob").help("Count only the files that match the glob pattern relative to the project root, like src/**."))
+        .arg(clap::Arg::with_name("exclude").long("exclude").takes_value(true).multiple(true).number_of_values(1).value_name("glob").help("Skip the files that match the glob pattern relative to the project root, like src/generated/** or **/*_pb.rs."))
+        .arg(clap::Arg::with_name("generated").long("generated").takes_value(true).multiple(true).number_of_values(1).value_name("glob").help("Count the files that match the glob pattern relative to the project root as generated lines, like src/proto/**."))
+        .arg(clap::Arg::with_name("jobs").long("jobs").short("j").takes_value(true).value_name("N").help("Number of threads for counting. The default is one thread per CPU."))
+        .arg(clap::Arg::with_name("follow_symlinks").long("follow-symlinks").conflicts_with("no_follow_symlinks").help("Follow the symlinks to files and folders. This is the default."))
+        .arg(clap::Arg::with_name("no_follow_symlinks").long("no-follow-symlinks").help("Skip the symlinks to files and folders."))
+        .arg(clap::Arg::with_name("no_cache").long("no-cache").help("Don't use the cache of counted files in target/lmake_lines_of_code/cache.json."))
+        .arg(clap::Arg::with_name("config").long("config").takes_value(true).value_name("file").help("Read the settings from this file instead of .lmake_lines_of_code.toml in the project root."))
+        .arg(clap::Arg::with_name("project_root").long("project-root").takes_value(true).value_name("dir").help("The folder with Cargo.toml. The default is the current directory."))
+        .arg(clap::Arg::with_name("categories").long("categories").takes_value(true).value_name("list").help("The badges in the output, separated by commas: code, doc_comments, comments, examples, tests."))
+        .arg(clap::Arg::with_name("output_format").long("output-format").takes_value(true).possible_values(&["markdown", "json"]).help("Print the markdown table and badges or the counts as JSON."))
+        .arg(clap::Arg::with_name("readme_target").long("readme-target").takes_value(true).multiple(true).number_of_values(1).value_name("file").help("Include the badges into this markdown file instead of README.md."))
+        .arg(clap::Arg::with_name("badge_style").long("badge-style").takes_value(true).value_name("style").help("The shields.io style: flat, flat-square, plastic, for-the-badge or social."))
+        .arg(clap::Arg::with_name("number_format").long("number-format").takes_value(true).possible_values(&["plain", "separated", "compact"]).help("The numbers in the badges, the table and the charts: 123456, 123,456 or 123k."))
+        .arg(clap::Arg::with_name("thousands_separator").long("thousands-separator").takes_value(true).value_name("separator").help("The thousands separator of --number-format separated. The default is a comma."))
+        .arg(clap::Arg::with_name("number_decimals").long("number-decimals").takes_value(true).value_name("N").help("The decimals of --number-format compact below 100 units, like 1.2M. The default is 1."))
+        .arg(clap::Arg::with_name("ratio_badges").long("ratio-badges").takes_value(true).value_name("list").help("Ratio badges, separated by commas: doc_comments_per_code, comment_density, tests_to_code, examples_to_code."))
+        .arg(clap::Arg::with_name("ratio_threshold").long("ratio-threshold").takes_value(true).multiple(true).number_of_values(1).value_name("ratio=yellow:green").help("The ratio badge is red below yellow, yellow below green and green above, like tests_to_code=0.2:0.5."))
+        .arg(clap::Arg::with_name("save_baseline").long("save-baseline").takes_value(true).value_name("file").help("Write the lines of the workspace, every member and every file as JSON, like loc-baseline.json."))
+        .arg(clap::Arg::with_name("compare_baseline").long("compare-baseline").takes_value(true).value_name("file").help("Fail if tests lines or doc comments dropped compared to the baseline file."))
+        .arg(clap::Arg::with_name("max_drop").long("max-drop").takes_value(true).multiple(true).number_of_values(1).value_name("metric=percent").help("The allowed drop for --compare-baseline, like tests_lines=5. The default is 0 for tests_lines and src_doc_comment_lines."))
+        .arg(clap::Arg::with_name("history_log").long("history-log").takes_value(true).value_name("file").help("Append the lines of this run to the JSON lines file, like target/loc-history.jsonl."))
+        .arg(clap::Arg::with_name("list_files").long("list-files").help("List every counted file and its kind: src, tests or examples."))
+        .arg(clap::Arg::with_name("staged").long("staged").help("Count only the lines added and removed in the git index (git diff --cached)."))
+        .subcommand(
+            clap::SubCommand::with_name("install-hook")
+                .about("Writes the git pre-commit hook that refreshes the README badges.")
+                .arg(clap::Arg::with_name("force").long("force").help("Overwrite an existing pre-commit hook.")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("history")
+                .about("LOC history over git tags or every Nth commit on a branch.")
+                .arg(clap::Arg::with_name("branch").long("branch").takes_value(true).value_name("branch").help("Walk the commits on this branch instead of the tags."))
+                .arg(clap::Arg::with_name("every").long("every").takes_value(true).value_name("N").default_value("1").help("Every Nth commit on the branch."))
+                .arg(clap::Arg::with_name("csv").long("csv").takes_value(true).value_name("file").help("Write the history as CSV."))
+                .arg(clap::Arg::with_name("json").long("json").takes_value(true).value_name("file").help("Write the history as JSON.")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("diff")
+                .about("LOC delta between two git revisions: added, removed and net lines for every category.")
+                .arg(clap::Arg::with_name("range").required(true).value_name("from..to").help("Revisions like v1.0.0..HEAD. Without .. the second revision is HEAD.")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("watch")
+                .about("Re-counts the lines when the files change and prints the table.")
+                .arg(clap::Arg::with_name("readme").long("readme").help("Keep the shield badges in README.md updated."))
+                .arg(clap::Arg::with_name("debounce").long("debounce").takes_value(true).value_name("ms").default_value("500").help("Wait until the files are quiet for this many milliseconds.")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("check-policy")
+                .about("Checks the [[policy]] rules of the config and fails if a rule is violated."),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("trend")
+                .about("Charts of the lines from the history log: ASCII in the terminal and SVG.")
+                .arg(clap::Arg::with_name("svg").long("svg").takes_value(true).value_name("file").help("Write the SVG line chart with one line per category.")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("blame")
+                .about("Per-author lines of code, doc comments, comments and tests with git blame."),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("churn")
+                .about("Lines added and deleted per category, per week and per file.")
+                .arg(clap::Arg::with_name("since").long("since").takes_value(true).value_name("date").default_value("1 month ago").help("Commits since the date, like 2020-08-01 or \"3 months ago\".")),
+        )
         .get_matches();
 
     let link = arguments.value_of("link").unwrap_or("");
 
     println!("---- {} start ----", Green.paint(env!("CARGO_PKG_NAME")));
-    let app = AppObject::new();
-    let _text_to_include = app.main(link);
+    let mut app = match build_app(&arguments) {
+        Ok(app) => app,
+        Err(e) => {
+            println!("{}", Red.paint(e.to_string()));
+            std::process::exit(1);
+        }
+    };
+    app.rev = arguments.value_of("rev").unwrap_or("").to_string();
+    if !app.rev.is_empty() {
+        if let Err(e) = app.verify_rev(&app.rev) {
+            println!("{}", Red.paint(e.to_string()));
+            std::process::exit(1);
+        }
+    }
+    // the count cannot return an error, so git is checked before
+    if app.file_filter == FileFilter::Tracked {
+        if let Err(e) = git_tracked_files(&app.project_root) {
+            println!("{}", Red.paint(e.to_string()));
+            std::process::exit(1);
+        }
+    }
+    for badge_link in arguments.values_of("badge_link").into_iter().flatten() {
+        if let Err(e) = app.badge_links.set_from_arg(badge_link) {
+            println!("{}", Red.paint(e.to_string()));
+            std::process::exit(1);
+        }
+    }
+    let result = match arguments.subcommand() {
+        ("history", Some(sub_arguments)) => run_history(&app, sub_arguments),
+        ("diff", Some(sub_arguments)) => run_diff(&app, sub_arguments),
+        ("churn", Some(sub_arguments)) => app
+            .churn(sub_arguments.value_of("since").unwrap_or("1 month ago"))
+            .map(|churn| println!("{}", churn_to_md_tables(&churn))),
+        ("install-hook", Some(sub_arguments)) => app
+            .install_pre_commit_hook(sub_arguments.is_present("force"))
+            .map(|_| ()),
+        ("watch", Some(sub_arguments)) => run_watch(&app, link, sub_arguments),
+        ("check-policy", Some(_sub_arguments)) => run_check_policy(&app),
+        ("trend", Some(sub_arguments)) => run_trend(&app, sub_arguments),
+        ("blame", Some(_sub_arguments)) => app
+            .blame_authors()
+            .map(|authors| println!("{}", authors_to_md_table(&authors))),
+        _ if arguments.is_present("save_baseline") || arguments.is_present("compare_baseline") => {
+            run_baseline(&app, &arguments)
+        }
+        _ if arguments.is_present("list_files") => app
+            .file_system
+            .current_dir()
+            .map_err(anyhow::Error::from)
+            .and_then(|dir| {
+                app.workspace_or_project_files()
+                    .map(|files| println!("{}", project_files_to_md_table(&files, &dir)))
+            }),
+        _ if arguments.is_present("staged") => app.staged_delta().map(|delta| {
+            println!("{}", delta_to_md_tables(&delta));
+            println!("{}", delta_to_changelog_text(&delta));
+        }),
+        _ => {
+            let _text_to_include = app.main(link);
+            append_history_log(&app)
+        }
+    };
+    if let Err(e) = result {
+        println!("{}", Red.paint(e.to_string()));
+        std::process::exit(1);
+    }
     println!("---- {} end ----", Green.paint(env!("CARGO_PKG_NAME")));
 }
 
+/// The settings from the CLI flags win over the env vars, the config file and Cargo.toml.
+fn build_app(arguments: &clap::ArgMatches) -> anyhow::Result<AppObject> {
+    let strings = |name: &str| -> Vec<String> {
+        arguments
+            .values_of(name)
+            .into_iter()
+            .flatten()
+            .map(String::from)
+            .collect()
+    };
+    let flag = |name: &str| -> Option<bool> {
+        if arguments.is_present(name) {
+            Some(true)
+        } else {
+            None
+        }
+    };
+    let file_filter = if arguments.is_present("tracked") {
+        Some("tracked".to_string())
+    } else if arguments.is_present("respect_ignore") {
+        Some("respect-ignore".to_string())
+    } else {
+        None
+    };
+    let jobs = match arguments.value_of("jobs") {
+        Some(jobs) => Some(
+            jobs.parse()
+                .map_err(|e| anyhow::anyhow!("Error: --jobs {}: {}", jobs, e))?,
+        ),
+        None => None,
+    };
+    let number_decimals = match arguments.value_of("number_decimals") {
+        Some(decimals) => Some(
+            decimals
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Error: --number-decimals {}: {}", decimals, e))?,
+        ),
+        None => None,
+    };
+    let readme = strings("readme_target");
+    let list = |name: &str| -> Option<Vec<String>> {
+        arguments.value_of(name).map(|list| {
+            list.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+    };
+    let mut baseline_max_drop = BTreeMap::new();
+    for arg in strings("max_drop").iter() {
+        let (metric, percent) = parse_max_drop(arg)?;
+        baseline_max_drop.insert(metric, percent);
+    }
+    let mut ratio_thresholds = BTreeMap::new();
+    for arg in strings("ratio_threshold").iter() {
+        let (name, thresholds) = parse_ratio_thresholds(arg)?;
+        ratio_thresholds.insert(name, thresholds);
+    }
+    let cli = ConfigLayer {
+        project_root: arguments.value_of("project_root").map(PathBuf::from),
+        config_file: arguments.value_of("config").map(PathBuf::from),
+        categories: list("categories"),
+        include: strings("include"),
+        exclude: strings("exclude"),
+        generated: strings("generated"),
+        file_filter,
+        output_format: arguments.value_of("output_format").map(String::from),
+        remote: arguments.value_of("remote").map(String::from),
+        readme: if readme.is_empty() {
+            None
+        } else {
+            Some(readme)
+        },
+        badge_style: arguments.value_of("badge_style").map(String::from),
+        number_format: arguments.value_of("number_format").map(String::from),
+        thousands_separator: arguments.value_of("thousands_separator").map(String::from),
+        number_decimals,
+        jobs,
+        no_cache: flag("no_cache"),
+        follow_symlinks: if arguments.is_present("no_follow_symlinks") {
+            Some(false)
+        } else {
+            flag("follow_symlinks")
+        },
+        history_log: arguments.value_of("history_log").map(String::from),
+        ratio_badges: list("ratio_badges"),
+        ratio_thresholds,
+        baseline_max_drop,
+        // the policy rules are only in the config files
+        ..ConfigLayer::default()
+    };
+    // env::vars() panics on env vars that are not UTF-8, they are skipped
+    let env_vars = env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)));
+    let config = Config::load(&RealFileSystem::default(), env_vars, cli)?;
+    AppObject::builder().config(config).build()
+}
+
+/// watch subcommand: runs until ctrl+c.
+fn run_watch(app: &AppObject, link: &str, arguments: &clap::ArgMatches) -> anyhow::Result<()> {
+    let debounce = arguments.value_of("debounce").unwrap_or("500");
+    let debounce: u64 = debounce
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Error: --debounce {}: {}", debounce, e))?;
+    app.watch(
+        link,
+        arguments.is_present("readme"),
+        std::time::Duration::from_millis(debounce),
+    )
+}
+
+/// Appends the lines to the history log once per run, if the history log is enabled.
+/// Only the runs that count the working tree append. The count uses the cache.
+fn append_history_log(app: &AppObject) -> anyhow::Result<()> {
+    if app.history_log.is_empty() || !app.rev.is_empty() {
+        return Ok(());
+    }
+    app.append_history_log(&app.workspace_or_project_count_lines())
+}
+
+/// check-policy subcommand: prints the violations and fails if there are any.
+fn run_check_policy(app: &AppObject) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !app.policies.is_empty(),
+        "Error: no [[policy]] rules in the config files or Cargo.toml"
+    );
+    append_history_log(app)?;
+    let violations = app.check_policy()?;
+    if violations.is_empty() {
+        println!(
+            "{}",
+            Green.paint(format!(
+                "All {} policy rules are satisfied.",
+                app.policies.len()
+            ))
+        );
+        return Ok(());
+    }
+    println!("{}", violations_to_md_table(&violations));
+    anyhow::bail!("Error: {} policy violations", violations.len())
+}
+
+/// --compare-baseline prints the regressions and fails if the workspace or a member regressed.
+/// --save-baseline writes the new baseline.
+fn run_baseline(app: &AppObject, arguments: &clap::ArgMatches) -> anyhow::Result<()> {
+    append_history_log(app)?;
+    if let Some(file_name) = arguments.value_of("compare_baseline") {
+        let regressions = app.compare_baseline(file_name)?;
+        if regressions.is_empty() {
+            println!(
+                "{}",
+                Green.paint(format!("No regressions compared to {}.", file_name))
+            );
+        } else {
+            println!("{}", regressions_to_md_table(&regressions));
+        }
+        let failing = regressions
+            .iter()
+            .filter(|r| r.scope != PolicyScope::File)
+            .count();
+        anyhow::ensure!(
+            failing == 0,
+            "Error: {} regressions compared to {}",
+            failing,
+            file_name
+        );
+    }
+    if let Some(file_name) = arguments.value_of("save_baseline") {
+        app.save_baseline(file_name)?;
+        println!("write file: {}", Green.paint(file_name));
+    }
+    Ok(())
+}
+
+/// trend subcommand: prints the ASCII charts and writes the SVG chart.
+fn run_trend(app: &AppObject, arguments: &clap::ArgMatches) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !app.history_log.is_empty(),
+        "Error: no history log. Use --history-log file or history_log in the config."
+    );
+    let entries = app.read_history_log(&app.history_log)?;
+    println!("{}", trend_to_ascii_charts(&entries, &app.number_format));
+    if let Some(file_name) = arguments.value_of("svg") {
+        app.file_system.write(
+            Path::new(file_name),
+            trend_to_svg(&entries, &app.number_format).as_bytes(),
+        )?;
+        println!("write file: {}", Green.paint(file_name));
+    }
+    Ok(())
+}
+
+/// history subcommand: prints the markdown table and writes the CSV and JSON files.
+fn run_history(app: &AppObject, arguments: &clap::ArgMatches) -> anyhow::Result<()> {
+    let source = match arguments.value_of("branch") {
+        Some(branch) => HistorySource::Commits {
+            branch: branch.to_string(),
+            every: arguments.value_of("every").unwrap_or("1").parse()?,
+        },
+        None => HistorySource::Tags,
+    };
+    let history = app.history(&source)?;
+    println!("{}", history_to_md_table(&history));
+    if let Some(file_name) = arguments.value_of("csv") {
+        app.file_system
+            .write(Path::new(file_name), history_to_csv(&history).as_bytes())?;
+        println!("write file: {}", Green.paint(file_name));
+    }
+    if let Some(file_name) = arguments.value_of("json") {
+        app.file_system
+            .write(Path::new(file_name), history_to_json(&history)?.as_bytes())?;
+        println!("write file: {}", Green.paint(file_name));
+    }
+    Ok(())
+}
+
+/// diff subcommand: prints the markdown tables and the changelog sentence.
+fn run_diff(app: &AppObject, arguments: &clap::ArgMatches) -> anyhow::Result<()> {
+    let delta = app.diff_revisions(arguments.value_of("range").unwrap_or("HEAD"))?;
+    println!("{}", delta_to_md_tables(&delta));
+    println!("{}", delta_to_changelog_text(&delta));
+    Ok(())
+}
+
 // region: different function code for Linux and Windows
 #[cfg(target_family = "windows")]
 /// only on windows "enable ansi support" must be called
@@ -0,0 +1,225 @@
+// blame_mod.rs
+//! Per-author line attribution by category.
+//!
+//! For every counted file runs `git blame --line-porcelain`
+//! and attributes the category of every line to the author of that line.
+//! The lines are classified like diff: the generated patterns, the include! targets and the member settings apply.
+//! The identities are merged with the .mailmap file and by email.
+
+use crate::count_lines_mod::*;
+use crate::git_rev_mod::*;
+use crate::AppObject;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Yellow};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// The lines of one author for every category.
+pub struct AuthorLines {
+    pub name: String,
+    pub email: String,
+    pub lines_of_code: LinesOfCode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// One line of .mailmap:
+/// the proper name and email replace the commit name and email.
+struct MailmapEntry {
+    proper_name: Option<String>,
+    proper_email: Option<String>,
+    commit_name: Option<String>,
+    commit_email: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// The parsed .mailmap file.
+pub struct Mailmap {
+    entries: Vec<MailmapEntry>,
+}
+
+impl Mailmap {
+    /// Parses the .mailmap content. All 4 forms of lines are supported:
+    /// `Proper Name <commit@email>`
+    /// `<proper@email> <commit@email>`
+    /// `Proper Name <proper@email> <commit@email>`
+    /// `Proper Name <proper@email> Commit Name <commit@email>`
+    pub fn parse(content: &str) -> Mailmap {
+        let mut entries = vec![];
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            // split into: name, email, name, email
+            let mut names = vec![];
+            let mut emails = vec![];
+            let mut rest = line;
+            while let Some(start) = rest.find('<') {
+                let end = match rest[start..].find('>') {
+                    Some(end) => start + end,
+                    None => break,
+                };
+                names.push(rest[..start].trim().to_string());
+                emails.push(rest[start + 1..end].trim().to_string());
+                rest = &rest[end + 1..];
+            }
+            let non_empty = |s: &String| if s.is_empty() { None } else { Some(s.clone()) };
+            match emails.len() {
+                1 => entries.push(MailmapEntry {
+                    proper_name: non_empty(&names[0]),
+                    proper_email: None,
+                    commit_name: None,
+                    commit_email: emails[0].clone(),
+                }),
+                2 => entries.push(MailmapEntry {
+                    proper_name: non_empty(&names[0]),
+                    proper_email: non_empty(&emails[0]),
+                    commit_name: non_empty(&names[1]),
+                    commit_email: emails[1].clone(),
+                }),
+                _ => {}
+            }
+        }
+        Mailmap { entries }
+    }
+    /// Returns the proper name and email for the commit name and email.
+    pub fn map(&self, name: &str, email: &str) -> (String, String) {
+        // the entry with the commit name is more specific than the entry with only the email
+        let entry = self
+            .entries
+            .iter()
+            .filter(|e| e.commit_email.eq_ignore_ascii_case(email))
+            .filter(|e| e.commit_name.iter().all(|n| n == name))
+            .max_by_key(|e| e.commit_name.is_some());
+        match entry {
+            Some(e) => (
+                e.proper_name.clone().unwrap_or_else(|| name.to_string()),
+                e.proper_email.clone().unwrap_or_else(|| email.to_string()),
+            ),
+            None => (name.to_string(), email.to_string()),
+        }
+    }
+}
+
+impl AppObject {
+    /// Returns the lines for every author, sorted by the count of all lines.
+    /// Files that are not committed are skipped.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let authors = app.blame_authors().unwrap();
+    /// println!("{}", authors_to_md_table(&authors));
+    /// ```
+    pub fn blame_authors(&self) -> anyhow::Result<Vec<AuthorLines>> {
+        let mailmap = Mailmap::parse(
+            &self
+                .file_system
+                .read_to_string(Path::new(".mailmap"))
+                .unwrap_or_default(),
+        );
+        // the lines are classified like diff: generated patterns, include! targets and member settings
+        let head_entries = git_ls_tree(&self.project_root, "HEAD").unwrap_or_default();
+        let members = workspace_members_in_tree(&self.project_root, &head_entries)?;
+        let included = self.included_at_rev("HEAD", &head_entries, &members)?;
+        let current_dir = self.file_system.current_dir()?;
+        let mut authors: Vec<AuthorLines> = vec![];
+        // the identity is the lowercase email after mailmap
+        let mut index_by_email: HashMap<String, usize> = HashMap::new();
+        for project_file in self.workspace_or_project_files()?.iter() {
+            let path = project_file.path.to_string_lossy();
+            let output = match git_output(
+                &self.project_root,
+                &["blame", "--line-porcelain", "--", &path],
+            ) {
+                Ok(o) => o,
+                Err(e) => {
+                    println!("{}", Yellow.paint(e.to_string()));
+                    continue;
+                }
+            };
+            let line_authors = parse_blame_line_porcelain(&output);
+            let content = self.file_system.read(&project_file.path)?;
+            let categories = self.member_line_categories(
+                &project_file.member,
+                &relative_path(&current_dir, &project_file.path),
+                project_file.file_kind,
+                &content,
+                &included,
+            );
+            // the lines of every author in this file
+            let mut file_authors: BTreeMap<usize, LinesOfCode> = BTreeMap::new();
+            for ((name, email), category) in line_authors.iter().zip(categories.iter()) {
+                let (name, email) = mailmap.map(name, email);
+                let index = *index_by_email
+                    .entry(email.to_lowercase())
+                    .or_insert_with(|| {
+                        authors.push(AuthorLines {
+                            name,
+                            email,
+                            ..Default::default()
+                        });
+                        authors.len() - 1
+                    });
+                file_authors.entry(index).or_default().add_line(*category);
+            }
+            for (index, lines_of_code) in file_authors.into_iter() {
+                authors[index].lines_of_code +=
+                    self.member_lines(&project_file.member, lines_of_code);
+            }
+        }
+        // an author can have lines only in the categories that the member does not count
+        authors.retain(|a| a.lines_of_code != LinesOfCode::default());
+        authors
+            .sort_by_key(|a| std::cmp::Reverse(a.lines_of_code.as_array().iter().sum::<usize>()));
+        // return
+        Ok(authors)
+    }
+}
+
+/// Parses the output of `git blame --line-porcelain`.
+/// Returns the author name and email for every line of the file in order.
+pub fn parse_blame_line_porcelain(output: &str) -> Vec<(String, String)> {
+    let mut line_authors = vec![];
+    let mut name = String::new();
+    let mut email = String::new();
+    for line in output.lines() {
+        if let Some(n) = line.strip_prefix("author ") {
+            name = n.to_string();
+        } else if let Some(e) = line.strip_prefix("author-mail ") {
+            email = e.trim_start_matches('<').trim_end_matches('>').to_string();
+        } else if line.starts_with('\t') {
+            // the content line ends the block of one line
+            line_authors.push((name.clone(), email.clone()));
+        }
+    }
+    // return
+    line_authors
+}
+
+/// Returns the markdown table with one row for every author.
+pub fn authors_to_md_table(authors: &[AuthorLines]) -> String {
+    let mut table = String::from(
+        "
+| author | src code | doc comments | comments | examples | tests | generated |
+| :----- | -------: | -----------: | -------: | -------: | ----: | --------: |
+",
+    );
+    for a in authors.iter() {
+        let v = &a.lines_of_code;
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} |\n",
+            a.name,
+            v.src_code_lines,
+            v.src_doc_comment_lines,
+            v.src_comment_lines,
+            v.examples_lines,
+            v.tests_lines,
+            v.generated_lines
+        ));
+    }
+    // return
+    table
+}
@@ -0,0 +1,136 @@
+// cache_mod.rs
+//! Incremental counting with an on-disk cache.
+//!
+//! The cache stores the count of lines of every file, keyed by path, size, mtime and content hash.
+//! Only the changed files are read and classified again.
+//! The cache is invalidated when the version of this tool or the cache format version changes.
+
+use crate::count_lines_mod::*;
+use crate::generated_mod::*;
+
+use crate::file_system_mod::FileSystem;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The cache file for the count of lines of every file.
+pub const FILE_CACHE_FILE: &str = "target/lmake_lines_of_code/cache.json";
+
+/// Bump this on every change of the classification rules or of the cache format,
+/// so the counts of the old rules are not used anymore.
+pub const CACHE_FORMAT_VERSION: u32 = 2;
+
+/// A file modified less than this before the cache was written can change
+/// without changing size and mtime, so the content hash is checked.
+const RACY_NANOS: u64 = 2_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The cached count of one file.
+pub(crate) struct CacheEntry {
+    pub(crate) size: u64,
+    /// modification time in nanoseconds since the unix epoch
+    pub(crate) mtime: u64,
+    /// FNV-1a hash of the content
+    pub(crate) hash: u64,
+    pub(crate) counted: CountedFile,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The cache for all the counted files. The key is the file path.
+pub(crate) struct FileCache {
+    pub(crate) version: String,
+    /// the time when the cache was written in nanoseconds since the unix epoch
+    pub(crate) written: u64,
+    pub(crate) files: BTreeMap<String, CacheEntry>,
+}
+
+impl FileCache {
+    /// Reads the cache. A missing or corrupt cache, or a cache from another version, is just empty.
+    pub(crate) fn read(file_system: &dyn FileSystem, cache_path: &Path) -> FileCache {
+        file_system
+            .read_to_string(cache_path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<FileCache>(&s).ok())
+            .filter(|cache| cache.version == cache_version())
+            .unwrap_or_default()
+    }
+    /// Writes the cache with the current time and version.
+    pub(crate) fn write(
+        &mut self,
+        file_system: &dyn FileSystem,
+        cache_path: &Path,
+    ) -> anyhow::Result<()> {
+        self.version = cache_version();
+        self.written = nanos_since_epoch(SystemTime::now());
+        file_system.write(cache_path, serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+    /// Returns the count of the file from the cache or reads and classifies the file.
+    /// The file is not read if size and mtime did not change.
+    /// If they changed, but the content hash is the same, the file is not classified again.
+    pub(crate) fn count_file(
+        &self,
+        file_system: &dyn FileSystem,
+        path: &Path,
+        relative_path: &str,
+        file_kind: FileKind,
+    ) -> anyhow::Result<CacheEntry> {
+        let metadata = file_system.metadata(path)?;
+        let size = metadata.len;
+        let mtime = metadata.modified;
+        let cached = self.files.get(path.to_string_lossy().as_ref());
+        if let Some(entry) = cached {
+            if entry.size == size
+                && entry.mtime == mtime
+                && mtime.saturating_add(RACY_NANOS) < self.written
+            {
+                return Ok(entry.clone());
+            }
+        }
+        let content = file_system.read(path)?;
+        let hash = fnv1a_hash(&content);
+        let counted = match cached {
+            Some(entry) if entry.hash == hash && entry.counted.relative_path == relative_path => {
+                entry.counted.clone()
+            }
+            _ => count_one_file(relative_path, file_kind, &content),
+        };
+        // return
+        Ok(CacheEntry {
+            size,
+            mtime,
+            hash,
+            counted,
+        })
+    }
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// The 64-bit FNV-1a hash. It is stable between versions of Rust, unlike the std hasher.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// assert_eq!(fnv1a_hash(b""), 0xcbf2_9ce4_8422_2325);
+/// assert_ne!(fnv1a_hash(b"a"), fnv1a_hash(b"b"));
+/// ```
+pub fn fnv1a_hash(content: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in content.iter() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// The version of the cached counts: the tool version and the cache format version.
+pub(crate) fn cache_version() -> String {
+    format!("{}-{}", env!("CARGO_PKG_VERSION"), CACHE_FORMAT_VERSION)
+}
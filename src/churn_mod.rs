@@ -0,0 +1,248 @@
+// churn_mod.rs
+//! Churn report: lines added and deleted per category, per week and per file.
+//!
+//! The commits and their changes are read from one `git log -p --since` stream.
+//! The changed lines of every commit are classified like in the diff subcommand.
+//! Files with high churn and no test growth are easy to spot.
+
+use crate::diff_mod::*;
+use crate::git_rev_mod::*;
+use crate::AppObject;
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::process::Stdio;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Added and removed lines since the date: total, per week and per file.
+pub struct ChurnReport {
+    pub since: String,
+    pub total: LinesOfCodeDelta,
+    /// key is the Monday of the week: 2020-08-17
+    pub per_week: BTreeMap<String, LinesOfCodeDelta>,
+    /// key is the file path relative to the project root
+    pub per_file: BTreeMap<String, LinesOfCodeDelta>,
+}
+
+impl AppObject {
+    /// Returns the churn since the date, like `2020-08-01` or `3 months ago`.
+    /// Merge commits are skipped, because their changes are already in the merged commits.
+    /// All the commits are read from one `git log -p` stream.
+    /// The blobs of every commit are read with one `git cat-file --batch` process.
+    /// The workspace members, their settings and the include! targets are read at HEAD.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let churn = app.churn("1 week ago").unwrap();
+    /// println!("{}", churn_to_md_tables(&churn));
+    /// ```
+    pub fn churn(&self, since: &str) -> anyhow::Result<ChurnReport> {
+        // before the first commit there is no HEAD
+        let head_entries = git_ls_tree(&self.project_root, "HEAD").unwrap_or_default();
+        let members = workspace_members_in_tree(&self.project_root, &head_entries)?;
+        let included = self.included_at_rev("HEAD", &head_entries, &members)?;
+        let since_arg = format!("--since={}", since);
+        let mut args = vec![
+            "log",
+            "-p",
+            // the root commit is a diff from the empty tree, also with log.showRoot = false
+            "--root",
+            "--no-merges",
+            "--reverse",
+            "--full-index",
+            // every commit starts with a line: NUL commit date
+            "--format=%x00%cI",
+            &since_arg,
+        ];
+        // the same diff options as the diff subcommand, without the word diff
+        args.extend(&GIT_DIFF_U0_ARGS[1..]);
+        let mut child = git_command(&self.project_root)
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut churn = ChurnReport {
+            since: since.to_string(),
+            ..Default::default()
+        };
+        let stdout = match child.stdout.take() {
+            Some(stdout) => stdout,
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!("Error: git log without stdout");
+            }
+        };
+        if let Err(e) = self.read_churn_log(stdout, &members, &included, &mut churn) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        let output = child.wait_with_output()?;
+        anyhow::ensure!(
+            output.status.success(),
+            "Error: git log: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // return
+        Ok(churn)
+    }
+    /// Reads the `git log -p` stream commit by commit and adds every commit to the churn.
+    fn read_churn_log(
+        &self,
+        stdout: impl Read,
+        members: &[String],
+        included: &HashSet<String>,
+        churn: &mut ChurnReport,
+    ) -> anyhow::Result<()> {
+        let mut date: Option<String> = None;
+        let mut diff_output = String::new();
+        for line in BufReader::new(stdout).split(b'\n') {
+            // the content of files can be invalid UTF-8
+            let line = String::from_utf8_lossy(&line?).into_owned();
+            if let Some(next_date) = line.strip_prefix('\0') {
+                if let Some(date) = date.take() {
+                    self.add_commit_churn(&date, &diff_output, members, included, churn)?;
+                }
+                date = Some(next_date.to_string());
+                diff_output.clear();
+            } else {
+                diff_output.push_str(&line);
+                diff_output.push('\n');
+            }
+        }
+        if let Some(date) = date {
+            self.add_commit_churn(&date, &diff_output, members, included, churn)?;
+        }
+        Ok(())
+    }
+    /// Classifies the changed lines of one commit and adds them to the week and the files.
+    fn add_commit_churn(
+        &self,
+        date: &str,
+        diff_output: &str,
+        members: &[String],
+        included: &HashSet<String>,
+        churn: &mut ChurnReport,
+    ) -> anyhow::Result<()> {
+        let file_diffs = parse_diff_u0(diff_output);
+        let old_side = DiffSide {
+            oids: file_diffs
+                .iter()
+                .filter_map(|d| Some((d.old_path.clone()?, d.old_oid.clone()?)))
+                .collect(),
+            included,
+        };
+        let new_side = DiffSide {
+            oids: file_diffs
+                .iter()
+                .filter_map(|d| Some((d.new_path.clone()?, d.new_oid.clone()?)))
+                .collect(),
+            included,
+        };
+        let mut delta = RevisionsDelta::default();
+        self.add_file_diffs(&mut delta, &file_diffs, &old_side, &new_side, members)?;
+        churn.total += &delta.total;
+        *churn.per_week.entry(week_start(date)).or_default() += &delta.total;
+        for (path, file_delta) in delta.per_file.iter() {
+            *churn.per_file.entry(path.clone()).or_default() += file_delta;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the Monday of the week for the ISO 8601 date: 2020-08-22T10:00:00+02:00 -> 2020-08-17.
+/// The time and the time zone are ignored.
+pub fn week_start(date: &str) -> String {
+    let parts: Vec<i64> = date
+        .get(..10)
+        .unwrap_or(date)
+        .split('-')
+        .filter_map(|p| p.parse().ok())
+        .collect();
+    if let [year, month, day] = parts.as_slice() {
+        let days = days_from_civil(*year, *month, *day);
+        // 1970-01-01 was a Thursday, the weekday 0 is Monday
+        let weekday = (days + 3).rem_euclid(7);
+        let (year, month, day) = civil_from_days(days - weekday);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    } else {
+        date.to_string()
+    }
+}
+
+/// Days since 1970-01-01 for the date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date for the days since 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Returns the markdown tables for the churn: per week and per file.
+/// The files are sorted by churn (added plus removed lines).
+/// A file with churn in code, but no added tests, is marked with "no test growth".
+pub fn churn_to_md_tables(churn: &ChurnReport) -> String {
+    let mut text = format!("\n## Churn since {}\n\n", churn.since);
+    text.push_str("| week | src code | doc comments | comments | examples | tests | generated |\n| :--- | ---: | ---: | ---: | ---: | ---: | ---: |\n");
+    for (week, delta) in churn.per_week.iter() {
+        text.push_str(&format!("| {} |{}\n", week, churn_cells(delta)));
+    }
+    text.push_str("\n| file | churn | src code | doc comments | comments | examples | tests | generated | |\n| :--- | ---: | ---: | ---: | ---: | ---: | ---: | ---: | :--- |\n");
+    let mut files: Vec<(&String, &LinesOfCodeDelta)> = churn.per_file.iter().collect();
+    files.sort_by_key(|(_, delta)| std::cmp::Reverse(churn_lines(delta)));
+    for (path, delta) in files.iter() {
+        let code_churn = delta.added.src_code_lines + delta.removed.src_code_lines;
+        let warning = if code_churn > 0 && delta.added.tests_lines == 0 {
+            "no test growth"
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "| {} | {} |{} {} |\n",
+            path,
+            churn_lines(delta),
+            churn_cells(delta),
+            warning
+        ));
+    }
+    // return
+    text
+}
+
+/// All added plus removed lines.
+pub fn churn_lines(delta: &LinesOfCodeDelta) -> usize {
+    delta.added.as_array().iter().sum::<usize>() + delta.removed.as_array().iter().sum::<usize>()
+}
+
+/// Table cells `+added -removed` for every category.
+fn churn_cells(delta: &LinesOfCodeDelta) -> String {
+    let added = delta.added.as_array();
+    let removed = delta.removed.as_array();
+    added
+        .iter()
+        .zip(removed.iter())
+        .map(|(a, r)| format!(" +{} -{} |", a, r))
+        .collect()
+}
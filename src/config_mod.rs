@@ -0,0 +1,687 @@
+// config_mod.rs
+//! The settings of the CLI and of the library in one model.
+//!
+//! The settings are merged from layers, the later layer wins:
+//! defaults, `[workspace.metadata.lmake_lines_of_code]` and `[package.metadata.lmake_lines_of_code]`
+//! in Cargo.toml, the config files from the outermost folder to the project root,
+//! the `LMAKE_LOC_*` env vars and the CLI flags.
+//! The include, exclude and generated patterns of all the layers are added together.
+//! Every workspace member can override the settings in its own Cargo.toml and config file.
+
+use crate::badge_link_mod::BADGE_NAMES;
+use crate::count_lines_mod::{CargoToml, LinesOfCode};
+use crate::file_pattern_mod::FilePatterns;
+use crate::file_system_mod::{FileSystem, RealFileSystem};
+use crate::ignore_mod::FileFilter;
+use crate::number_format_mod::NumberFormat;
+use crate::policy_mod::*;
+use crate::ratio_mod::*;
+use crate::AppObject;
+
+use ansi_term::Colour::Yellow;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The config file in the project root or in a parent folder.
+pub const CONFIG_FILE: &str = ".lmake_lines_of_code.toml";
+
+/// The prefix of the env vars, like `LMAKE_LOC_REMOTE`.
+pub const ENV_PREFIX: &str = "LMAKE_LOC_";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// What is printed after counting.
+pub enum OutputFormat {
+    /// the markdown table and the shield badges
+    #[default]
+    Markdown,
+    /// the LinesOfCode as JSON
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+    /// Parses markdown or json.
+    fn from_str(s: &str) -> anyhow::Result<OutputFormat> {
+        match s {
+            "markdown" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            _ => anyhow::bail!("Error: unknown output format {}. Use: markdown or json.", s),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// All the settings. Empty strings and lists mean the default behavior.
+pub struct Config {
+    /// the folder with Cargo.toml. Empty means the current directory.
+    pub project_root: PathBuf,
+    /// the badges in the output: code, doc_comments, comments, examples, tests. Empty means all.
+    pub categories: Vec<String>,
+    /// glob patterns relative to the project root
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub generated: Vec<String>,
+    pub file_filter: FileFilter,
+    pub output_format: OutputFormat,
+    /// name of the git remote for the badge link. Empty means: upstream, origin, first remote.
+    pub remote_name: String,
+    /// the files where the badges are included between the markers. Empty means README.md.
+    pub readme_targets: Vec<String>,
+    /// the shields.io style: flat, flat-square, plastic, for-the-badge or social. Empty means flat.
+    pub badge_style: String,
+    /// plain, separated or compact numbers in the badges, the markdown table and the charts
+    pub number_format: NumberFormat,
+    /// number of threads for counting. 0 means one thread per CPU.
+    pub jobs: usize,
+    pub no_cache: bool,
+    /// skip the symlinks. By default they are followed.
+    pub no_follow_symlinks: bool,
+    /// the ratio badges and the thresholds for their colors
+    pub ratio_badges: RatioBadges,
+    /// the rules for check-policy from all the sources
+    pub policies: Vec<PolicyRule>,
+    /// the allowed drop in percent by metric for --compare-baseline, on top of the default.
+    pub baseline_max_drop: BTreeMap<String, f64>,
+    /// the JSON lines file where every run appends its lines. Empty means no history log.
+    pub history_log: String,
+    /// the overrides of the workspace members. The key is the member folder.
+    pub members: BTreeMap<String, ConfigLayer>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// The settings from one source: the config file, the env vars or the CLI flags.
+/// None means the setting is not defined in this source.
+pub struct ConfigLayer {
+    /// only from the CLI and env vars, because the config file is in the project root
+    #[serde(skip)]
+    pub project_root: Option<PathBuf>,
+    /// only from the CLI and env vars. The config file to read instead of .lmake_lines_of_code.toml.
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
+    pub categories: Option<Vec<String>>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub generated: Vec<String>,
+    pub file_filter: Option<String>,
+    pub output_format: Option<String>,
+    pub remote: Option<String>,
+    pub readme: Option<Vec<String>>,
+    pub badge_style: Option<String>,
+    pub number_format: Option<String>,
+    pub thousands_separator: Option<String>,
+    pub number_decimals: Option<usize>,
+    pub jobs: Option<usize>,
+    pub no_cache: Option<bool>,
+    pub follow_symlinks: Option<bool>,
+    pub history_log: Option<String>,
+    pub ratio_badges: Option<Vec<String>>,
+    /// the thresholds by ratio name
+    #[serde(default)]
+    pub ratio_thresholds: BTreeMap<String, RatioThresholds>,
+    /// the `[[policy]]` rules for check-policy
+    #[serde(default)]
+    pub policy: Vec<PolicyRule>,
+    /// the allowed drop in percent by metric for --compare-baseline
+    #[serde(default)]
+    pub baseline_max_drop: BTreeMap<String, f64>,
+}
+
+impl ConfigLayer {
+    /// Parses the content of the config file.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let layer = ConfigLayer::from_toml(r#"
+    /// remote = "my_fork"
+    /// exclude = ["src/generated/**"]
+    /// "#).unwrap();
+    /// assert_eq!(layer.remote.unwrap(), "my_fork");
+    /// ```
+    pub fn from_toml(content: &str) -> anyhow::Result<ConfigLayer> {
+        toml::from_str(content).map_err(|e| anyhow::anyhow!("Error: config {}: {}", CONFIG_FILE, e))
+    }
+    /// The settings from `[workspace.metadata.lmake_lines_of_code]`
+    /// and then from `[package.metadata.lmake_lines_of_code]` in the Cargo.toml content.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let layer = ConfigLayer::from_cargo_toml(r#"
+    /// [workspace]
+    /// members = ["a"]
+    /// [workspace.metadata.lmake_lines_of_code]
+    /// remote = "upstream"
+    /// exclude = ["src/generated/**"]
+    /// "#).unwrap();
+    /// assert_eq!(layer.remote.unwrap(), "upstream");
+    /// ```
+    pub fn from_cargo_toml(cargo_toml: &str) -> anyhow::Result<ConfigLayer> {
+        let cargo_toml: CargoToml = toml::from_str(cargo_toml)?;
+        let workspace_config = cargo_toml
+            .workspace
+            .and_then(|workspace| workspace.metadata)
+            .and_then(|metadata| metadata.lmake_lines_of_code);
+        let package_config = cargo_toml
+            .package
+            .and_then(|package| package.metadata)
+            .and_then(|metadata| metadata.lmake_lines_of_code);
+        let mut layer = ConfigLayer::default();
+        for config in workspace_config.into_iter().chain(package_config) {
+            let config: ConfigLayer = config.try_into().map_err(|e| {
+                anyhow::anyhow!("Error: Cargo.toml metadata lmake_lines_of_code: {}", e)
+            })?;
+            layer.merge(config);
+        }
+        // return
+        Ok(layer)
+    }
+    /// Puts the other layer over this layer. The patterns are added.
+    pub fn merge(&mut self, other: ConfigLayer) {
+        fn over<T>(this: &mut Option<T>, other: Option<T>) {
+            if other.is_some() {
+                *this = other;
+            }
+        }
+        over(&mut self.project_root, other.project_root);
+        over(&mut self.config_file, other.config_file);
+        over(&mut self.categories, other.categories);
+        self.include.extend(other.include);
+        self.exclude.extend(other.exclude);
+        self.generated.extend(other.generated);
+        over(&mut self.file_filter, other.file_filter);
+        over(&mut self.output_format, other.output_format);
+        over(&mut self.remote, other.remote);
+        over(&mut self.readme, other.readme);
+        over(&mut self.badge_style, other.badge_style);
+        over(&mut self.number_format, other.number_format);
+        over(&mut self.thousands_separator, other.thousands_separator);
+        over(&mut self.number_decimals, other.number_decimals);
+        over(&mut self.jobs, other.jobs);
+        over(&mut self.no_cache, other.no_cache);
+        over(&mut self.follow_symlinks, other.follow_symlinks);
+        over(&mut self.history_log, other.history_log);
+        over(&mut self.ratio_badges, other.ratio_badges);
+        self.ratio_thresholds.extend(other.ratio_thresholds);
+        self.policy.extend(other.policy);
+        self.baseline_max_drop.extend(other.baseline_max_drop);
+    }
+    /// Reads the `LMAKE_LOC_*` env vars. Lists are separated by commas.
+    /// Other env vars are ignored, an unknown `LMAKE_LOC_*` var prints a warning and is ignored.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let vars = vec![
+    ///     ("LMAKE_LOC_EXCLUDE".to_string(), "src/a.rs, src/b.rs".to_string()),
+    ///     ("LMAKE_LOC_NO_CACHE".to_string(), "1".to_string()),
+    ///     ("HOME".to_string(), "/home/user".to_string()),
+    /// ];
+    /// let layer = ConfigLayer::from_env_vars(vars).unwrap();
+    /// assert_eq!(layer.exclude, vec!["src/a.rs", "src/b.rs"]);
+    /// assert_eq!(layer.no_cache, Some(true));
+    /// ```
+    pub fn from_env_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<ConfigLayer> {
+        let mut layer = ConfigLayer::default();
+        for (key, value) in vars {
+            let name = match key.strip_prefix(ENV_PREFIX) {
+                Some(name) => name,
+                None => continue,
+            };
+            let list = || -> Vec<String> {
+                value
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            };
+            match name {
+                "PROJECT_ROOT" => layer.project_root = Some(PathBuf::from(&value)),
+                "CONFIG" => layer.config_file = Some(PathBuf::from(&value)),
+                "CATEGORIES" => layer.categories = Some(list()),
+                "INCLUDE" => layer.include = list(),
+                "EXCLUDE" => layer.exclude = list(),
+                "GENERATED" => layer.generated = list(),
+                "FILE_FILTER" => layer.file_filter = Some(value.clone()),
+                "OUTPUT_FORMAT" => layer.output_format = Some(value.clone()),
+                "REMOTE" => layer.remote = Some(value.clone()),
+                "README" => layer.readme = Some(list()),
+                "BADGE_STYLE" => layer.badge_style = Some(value.clone()),
+                "NUMBER_FORMAT" => layer.number_format = Some(value.clone()),
+                "THOUSANDS_SEPARATOR" => layer.thousands_separator = Some(value.clone()),
+                "NUMBER_DECIMALS" => {
+                    layer.number_decimals = Some(
+                        value
+                            .parse()
+                            .map_err(|e| anyhow::anyhow!("Error: {} {}: {}", key, value, e))?,
+                    )
+                }
+                "JOBS" => {
+                    layer.jobs = Some(
+                        value
+                            .parse()
+                            .map_err(|e| anyhow::anyhow!("Error: {} {}: {}", key, value, e))?,
+                    )
+                }
+                "NO_CACHE" => layer.no_cache = Some(parse_bool(&key, &value)?),
+                "FOLLOW_SYMLINKS" => layer.follow_symlinks = Some(parse_bool(&key, &value)?),
+                "HISTORY_LOG" => layer.history_log = Some(value.clone()),
+                "BASELINE_MAX_DROP" => {
+                    for arg in list().iter() {
+                        let (metric, percent) = parse_max_drop(arg)?;
+                        layer.baseline_max_drop.insert(metric, percent);
+                    }
+                }
+                "RATIO_BADGES" => layer.ratio_badges = Some(list()),
+                "RATIO_THRESHOLDS" => {
+                    for arg in list().iter() {
+                        let (name, thresholds) = parse_ratio_thresholds(arg)?;
+                        layer.ratio_thresholds.insert(name, thresholds);
+                    }
+                }
+                _ => println!(
+                    "{}",
+                    Yellow.paint(format!("Warning: unknown env var {} is ignored.", key))
+                ),
+            }
+        }
+        Ok(layer)
+    }
+}
+
+/// Reads .lmake_lines_of_code.toml in the folder, if it exists.
+fn read_config_file(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+) -> anyhow::Result<Option<ConfigLayer>> {
+    let path = dir.join(CONFIG_FILE);
+    match file_system.read_to_string(&path) {
+        Ok(content) => ConfigLayer::from_toml(&content)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("{} in {}", e, path.display())),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Returns an error for an unknown category.
+fn check_categories(categories: &[String]) -> anyhow::Result<()> {
+    for category in categories.iter() {
+        if !BADGE_NAMES.contains(&category.as_str()) {
+            anyhow::bail!(
+                "Error: unknown category {}. Use: {}.",
+                category,
+                BADGE_NAMES.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parses `metric=percent`, like `tests_lines=5`.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// assert_eq!(parse_max_drop("tests_lines=5").unwrap(), ("tests_lines".to_string(), 5.0));
+/// ```
+pub fn parse_max_drop(arg: &str) -> anyhow::Result<(String, f64)> {
+    let (metric, percent) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Error: max drop {} is not metric=percent", arg))?;
+    let percent = percent
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Error: max drop {}: {}", arg, e))?;
+    // return
+    Ok((metric.trim().to_string(), percent))
+}
+
+fn parse_bool(key: &str, value: &str) -> anyhow::Result<bool> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" | "" => Ok(false),
+        _ => anyhow::bail!("Error: {} {} is not true or false", key, value),
+    }
+}
+
+impl Config {
+    /// Loads the config from all the sources. The CLI flags win over the env vars,
+    /// the env vars over the config files, the config files over Cargo.toml.
+    /// The config files are .lmake_lines_of_code.toml in the project root and in all the parent folders,
+    /// the nearest wins. The file given with `--config` or `LMAKE_LOC_CONFIG` replaces them and must exist.
+    /// The workspace members can override the settings in their Cargo.toml and config file.
+    pub fn load(
+        file_system: &dyn FileSystem,
+        env_vars: impl IntoIterator<Item = (String, String)>,
+        cli: ConfigLayer,
+    ) -> anyhow::Result<Config> {
+        let env = ConfigLayer::from_env_vars(env_vars)?;
+        let project_root = cli
+            .project_root
+            .clone()
+            .or_else(|| env.project_root.clone())
+            .unwrap_or_default();
+        let mut config = Config {
+            project_root: project_root.clone(),
+            ..Config::default()
+        };
+        let mut members = vec![];
+        if let Ok(cargo_toml) = file_system.read_to_string(&project_root.join("Cargo.toml")) {
+            config.merge(ConfigLayer::from_cargo_toml(&cargo_toml)?)?;
+            let cargo_toml: CargoToml = toml::from_str(&cargo_toml)?;
+            members = cargo_toml
+                .workspace
+                .map(|workspace| workspace.members)
+                .unwrap_or_default();
+        }
+        match cli.config_file.clone().or_else(|| env.config_file.clone()) {
+            Some(config_file) => {
+                let content = file_system.read_to_string(&config_file).map_err(|e| {
+                    anyhow::anyhow!("Error: config {}: {}", config_file.display(), e)
+                })?;
+                config.merge(ConfigLayer::from_toml(&content)?)?;
+            }
+            None => {
+                // from the outermost folder to the project root
+                let root = file_system.current_dir()?.join(&project_root);
+                let mut dirs: Vec<&Path> = root.ancestors().collect();
+                dirs.reverse();
+                for dir in dirs {
+                    if let Some(layer) = read_config_file(file_system, dir)? {
+                        config.merge(layer)?;
+                    }
+                }
+            }
+        }
+        for member in members.iter() {
+            let member_dir = project_root.join(member);
+            let mut layer = ConfigLayer::default();
+            if let Ok(cargo_toml) = file_system.read_to_string(&member_dir.join("Cargo.toml")) {
+                layer.merge(ConfigLayer::from_cargo_toml(&cargo_toml)?);
+            }
+            if let Some(member_layer) = read_config_file(file_system, &member_dir)? {
+                layer.merge(member_layer);
+            }
+            // the rules of a member apply to the member
+            for mut rule in std::mem::take(&mut layer.policy) {
+                if rule.members.is_empty() {
+                    rule.members = vec![member.clone()];
+                }
+                if rule.scope == PolicyScope::Workspace {
+                    rule.scope = PolicyScope::Member;
+                }
+                rule.validate()?;
+                config.policies.push(rule);
+            }
+            if layer != ConfigLayer::default() {
+                config.members.insert(member.clone(), layer);
+            }
+        }
+        config.merge(env)?;
+        config.merge(cli)?;
+        // return
+        Ok(config)
+    }
+    /// Applies the settings of the layer over the current settings.
+    /// The patterns are added, the other settings are replaced.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let mut config = Config::default();
+    /// config.merge(ConfigLayer::from_toml(r#"remote = "origin""#).unwrap()).unwrap();
+    /// config.merge(ConfigLayer::from_toml(r#"badge_style = "flat-square""#).unwrap()).unwrap();
+    /// assert_eq!(config.remote_name, "origin");
+    /// assert_eq!(config.badge_style, "flat-square");
+    /// ```
+    pub fn merge(&mut self, layer: ConfigLayer) -> anyhow::Result<()> {
+        if let Some(project_root) = layer.project_root {
+            self.project_root = project_root;
+        }
+        if let Some(categories) = layer.categories {
+            check_categories(&categories)?;
+            self.categories = categories;
+        }
+        self.include.extend(layer.include);
+        self.exclude.extend(layer.exclude);
+        self.generated.extend(layer.generated);
+        if let Some(file_filter) = layer.file_filter {
+            self.file_filter = file_filter.parse()?;
+        }
+        if let Some(output_format) = layer.output_format {
+            self.output_format = output_format.parse()?;
+        }
+        if let Some(remote) = layer.remote {
+            self.remote_name = remote;
+        }
+        if let Some(readme) = layer.readme {
+            self.readme_targets = readme;
+        }
+        if let Some(badge_style) = layer.badge_style {
+            self.badge_style = badge_style;
+        }
+        if let Some(number_format) = layer.number_format {
+            self.number_format.style = number_format.parse()?;
+        }
+        if let Some(thousands_separator) = layer.thousands_separator {
+            self.number_format.separator = thousands_separator;
+        }
+        if let Some(number_decimals) = layer.number_decimals {
+            self.number_format.decimals = number_decimals;
+        }
+        if let Some(jobs) = layer.jobs {
+            self.jobs = jobs;
+        }
+        if let Some(no_cache) = layer.no_cache {
+            self.no_cache = no_cache;
+        }
+        if let Some(follow_symlinks) = layer.follow_symlinks {
+            self.no_follow_symlinks = !follow_symlinks;
+        }
+        if let Some(history_log) = layer.history_log {
+            self.history_log = history_log;
+        }
+        if let Some(ratio_badges) = layer.ratio_badges {
+            self.ratio_badges.ratios = ratio_badges
+                .iter()
+                .map(|name| name.parse())
+                .collect::<anyhow::Result<Vec<Ratio>>>()?;
+        }
+        for (name, thresholds) in layer.ratio_thresholds {
+            self.ratio_badges
+                .thresholds
+                .insert(name.parse()?, thresholds);
+        }
+        for (metric, percent) in layer.baseline_max_drop {
+            metric_value(&LinesOfCode::default(), &metric)?;
+            self.baseline_max_drop.insert(metric, percent);
+        }
+        for rule in layer.policy {
+            rule.validate()?;
+            self.policies.push(rule);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// The settings of one workspace member that override the workspace settings.
+pub struct MemberOverride {
+    /// the workspace patterns plus the patterns of the member
+    pub file_patterns: FilePatterns,
+    /// the categories counted for this member, like without examples. Empty means all.
+    pub categories: Vec<String>,
+}
+
+/// Builds the AppObject from the Config.
+#[derive(Default)]
+pub struct AppObjectBuilder {
+    config: Config,
+    file_system: Option<Box<dyn FileSystem>>,
+}
+
+impl AppObject {
+    /// Returns the builder for an AppObject with a Config.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let config = Config {
+    ///     exclude: vec!["src/generated/**".to_string()],
+    ///     badge_style: "flat-square".to_string(),
+    ///     ..Config::default()
+    /// };
+    /// let app = AppObject::builder().config(config).build().unwrap();
+    /// assert!(!app.file_patterns.is_counted("src/generated/a.rs"));
+    /// ```
+    pub fn builder() -> AppObjectBuilder {
+        AppObjectBuilder::default()
+    }
+}
+
+impl AppObjectBuilder {
+    /// All the settings.
+    pub fn config(mut self, config: Config) -> AppObjectBuilder {
+        self.config = config;
+        self
+    }
+    /// The file system instead of the real file system.
+    pub fn file_system(mut self, file_system: Box<dyn FileSystem>) -> AppObjectBuilder {
+        self.file_system = Some(file_system);
+        self
+    }
+    /// Returns the AppObject. Returns an error for invalid glob patterns.
+    /// The real file system and git work in the project root.
+    /// The current directory of the process is never changed.
+    pub fn build(self) -> anyhow::Result<AppObject> {
+        let config = self.config;
+        let mut file_patterns = FilePatterns::default();
+        for pattern in config.include.iter() {
+            file_patterns.add_include(pattern)?;
+        }
+        for pattern in config.exclude.iter() {
+            file_patterns.add_exclude(pattern)?;
+        }
+        for pattern in config.generated.iter() {
+            file_patterns.add_generated(pattern)?;
+        }
+        let mut member_overrides = BTreeMap::new();
+        for (member, layer) in config.members.iter() {
+            let mut member_patterns = file_patterns.clone();
+            for pattern in layer.include.iter() {
+                member_patterns.add_include(pattern)?;
+            }
+            for pattern in layer.exclude.iter() {
+                member_patterns.add_exclude(pattern)?;
+            }
+            for pattern in layer.generated.iter() {
+                member_patterns.add_generated(pattern)?;
+            }
+            let categories = layer.categories.clone().unwrap_or_default();
+            check_categories(&categories)?;
+            member_overrides.insert(
+                member.clone(),
+                MemberOverride {
+                    file_patterns: member_patterns,
+                    categories,
+                },
+            );
+        }
+        let file_system = match self.file_system {
+            Some(file_system) => file_system,
+            None => {
+                let file_system = RealFileSystem::new(&config.project_root);
+                anyhow::ensure!(
+                    file_system.is_dir(&file_system.current_dir()?),
+                    "Error: project root {} is not a folder",
+                    config.project_root.display()
+                );
+                Box::new(file_system)
+            }
+        };
+        // return
+        Ok(AppObject {
+            project_root: config.project_root,
+            remote_name: config.remote_name,
+            file_filter: config.file_filter,
+            file_patterns,
+            jobs: config.jobs,
+            no_cache: config.no_cache,
+            no_follow_symlinks: config.no_follow_symlinks,
+            categories: config.categories,
+            output_format: config.output_format,
+            readme_targets: config.readme_targets,
+            badge_style: config.badge_style,
+            number_format: config.number_format,
+            ratio_badges: config.ratio_badges,
+            policies: config.policies,
+            baseline_max_drop: config.baseline_max_drop,
+            history_log: config.history_log,
+            member_overrides,
+            file_system,
+            ..AppObject::default()
+        })
+    }
+}
+
+impl AppObject {
+    /// The file patterns for the files of the workspace member. Empty member for a single project.
+    pub fn member_file_patterns(&self, member: &str) -> &FilePatterns {
+        match self.member_overrides.get(member) {
+            Some(member_override) => &member_override.file_patterns,
+            None => &self.file_patterns,
+        }
+    }
+    /// Returns only the categories that the workspace member counts.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let mut app = AppObject::new();
+    /// app.member_overrides.insert(
+    ///     "a".to_string(),
+    ///     MemberOverride { categories: vec!["code".to_string()], ..Default::default() },
+    /// );
+    /// let v = app.member_lines("a", LinesOfCode::new(1, 2, 3, 4, 5));
+    /// assert_eq!(v, LinesOfCode::new(1, 0, 0, 0, 0));
+    /// ```
+    pub fn member_lines(&self, member: &str, v: LinesOfCode) -> LinesOfCode {
+        let categories = match self.member_overrides.get(member) {
+            Some(member_override) if !member_override.categories.is_empty() => {
+                &member_override.categories
+            }
+            _ => return v,
+        };
+        let counted = |name: &str, lines: usize| {
+            if categories.iter().any(|c| c == name) {
+                lines
+            } else {
+                0
+            }
+        };
+        // return
+        LinesOfCode {
+            src_code_lines: counted("code", v.src_code_lines),
+            src_doc_comment_lines: counted("doc_comments", v.src_doc_comment_lines),
+            src_comment_lines: counted("comments", v.src_comment_lines),
+            tests_lines: counted("tests", v.tests_lines),
+            examples_lines: counted("examples", v.examples_lines),
+            generated_lines: v.generated_lines,
+        }
+    }
+}
@@ -1,29 +1,108 @@
 // count_lines_mod.rs
 //! Module with fn to count rust lines.
 
+use crate::badge_link_mod::*;
+use crate::cache_mod::*;
+use crate::generated_mod::*;
+use crate::git_remote_mod::*;
+use crate::ignore_mod::*;
+use crate::ratio_mod::*;
 use crate::utilsmod::*;
-use anyhow;
 
 #[allow(unused_imports)]
 use ansi_term::Colour::{Green, Yellow};
-use regex::Regex;
-use serde_derive::Deserialize;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::{env, fs, path::Path};
+use serde_derive::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
 use unwrap::unwrap;
 
 #[derive(Deserialize)]
-struct CargoToml {
-    workspace: Option<Workspace>,
+pub(crate) struct CargoToml {
+    pub(crate) package: Option<Package>,
+    pub(crate) workspace: Option<Workspace>,
 }
 
 #[derive(Deserialize)]
-struct Workspace {
-    members: Vec<String>,
+pub(crate) struct Package {
+    /// can be a string or an inherited table `{ workspace = true }`
+    pub(crate) repository: Option<toml::Value>,
+    pub(crate) homepage: Option<toml::Value>,
+    pub(crate) metadata: Option<Metadata>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Deserialize)]
+/// `[package.metadata]` or `[workspace.metadata]`.
+pub(crate) struct Metadata {
+    /// the ConfigLayer. A wrong config must not break the parsing of Cargo.toml.
+    pub(crate) lmake_lines_of_code: Option<toml::Value>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Workspace {
+    #[serde(default)]
+    pub(crate) members: Vec<String>,
+    pub(crate) package: Option<WorkspacePackage>,
+    pub(crate) metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct WorkspacePackage {
+    pub(crate) repository: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The kind of the rs file depends on the folder: src, tests or examples.
+pub enum FileKind {
+    /// code, doc comments, comments and unit tests after #[cfg(test)]
+    Src,
+    /// all lines are tests lines
+    Tests,
+    /// all lines are examples lines
+    Examples,
+}
+
+impl FileKind {
+    /// All the kinds in the order of counting.
+    pub fn all() -> [FileKind; 3] {
+        [FileKind::Src, FileKind::Tests, FileKind::Examples]
+    }
+    /// The folder inside the project, where this kind of files are.
+    pub fn folder_name(&self) -> &'static str {
+        match self {
+            FileKind::Src => "src",
+            FileKind::Tests => "tests",
+            FileKind::Examples => "examples",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// One rs file that is counted.
+pub struct ProjectFile {
+    /// workspace member. Empty for a single project.
+    pub member: String,
+    pub path: PathBuf,
+    pub file_kind: FileKind,
+}
+
+/// The path of the file relative to the project root, with / as separator.
+pub fn relative_path(project_path: &Path, path: &Path) -> String {
+    path.strip_prefix(project_path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// The excluded dirs: big folders and other folders with *.rs that are not code.
+pub fn exclude_dirs() -> Vec<String> {
+    vec![
+        "/.git".to_string(),
+        "/target".to_string(),
+        "/docs".to_string(),
+    ]
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Struct that contains 4 types of lines count: code, doc comments, comments, test and examples.
 pub struct LinesOfCode {
     /// lines with code in srs files
@@ -36,6 +115,33 @@ pub struct LinesOfCode {
     pub tests_lines: usize,
     /// all lines in examples files
     pub examples_lines: usize,
+    /// all lines in generated files, they are not counted as code
+    #[serde(default)]
+    pub generated_lines: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The lines of one workspace member. Empty member for a single project.
+pub struct MemberCount {
+    pub member: String,
+    pub lines_of_code: LinesOfCode,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The lines of one counted file.
+pub struct FileCount {
+    pub member: String,
+    /// path relative to the workspace root, with / as separator
+    pub path: String,
+    pub lines_of_code: LinesOfCode,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The lines of the workspace, of every member and of every file.
+pub struct CountReport {
+    pub total: LinesOfCode,
+    pub members: Vec<MemberCount>,
+    pub files: Vec<FileCount>,
 }
 
 use crate::AppObject;
@@ -45,188 +151,213 @@ impl TraitCountLines for AppObject {
     /// Returns the struct LinesOfCode for 4 types of lines:
     /// code, doc comments, comments, test and examples.
     /// Automatically detects if this is a workspace or single rust project.
+    /// Panics if the rev is not a commit. Check it first with verify_rev().
     ///
     /// ## Example
     ///
     /// ```
     /// use lmake_lines_of_code::*;
     ///
-    /// let app = AppObject{};
+    /// let app = AppObject::new();
     /// let v = app.workspace_or_project_count_lines();
     /// dbg!(&v);
     /// ```
     fn workspace_or_project_count_lines(&self) -> LinesOfCode {
-        let mut lines_of_code = LinesOfCode::default();
-
-        let current_dir = unwrap!(env::current_dir());
-        println!(
-            "current_dir: {}",
-            Yellow.paint(unwrap!(current_dir.to_str()))
-        );
-
-        // cargo toml contains the list of projects
-        let cargo_toml = unwrap!(fs::read_to_string("Cargo.toml"));
-        let cargo_toml: CargoToml = unwrap!(toml::from_str(&cargo_toml));
-        if let Some(workspace) = cargo_toml.workspace {
-            for member in workspace.members.iter() {
-                println!("{}", &member);
-                let v = Self::one_project_count_lines(&current_dir.join(member));
-                lines_of_code.src_code_lines += v.src_code_lines;
-                lines_of_code.src_doc_comment_lines += v.src_doc_comment_lines;
-                lines_of_code.src_comment_lines += v.src_comment_lines;
-                lines_of_code.tests_lines += v.tests_lines;
-                lines_of_code.examples_lines += v.examples_lines;
-            }
-        } else {
-            lines_of_code = Self::one_project_count_lines(&current_dir);
+        if !self.rev.is_empty() {
+            return unwrap!(self.workspace_or_project_count_lines_at_rev(&self.rev));
         }
         // return
-        lines_of_code
+        unwrap!(self.count_report()).total
     }
     /// Return the string for link for badges like: https://github.com/LucianoBestia/lmake_lines_of_code/.  
-    /// Get the output string after $ git remote -v.  
-    /// Then finds out the link to the repository with regex.  
+    /// Reads the remotes from .git/config or from the output of $ git remote -v.  
+    /// Chooses the remote by name or with the fallback order: upstream, origin, first remote.  
     /// Returns empty string if something goes wrong: no git, no remote,...  
     fn process_git_remote(&self) -> String {
-        let output = match self.git_remote_output() {
-            Ok(s) => s,
+        let remotes = match self.git_remotes() {
+            Ok(r) => r,
             Err(e) => {
                 println!("{}", e);
                 return "".to_string();
             }
         };
-        match self.regex_capture(output) {
-            Ok(s) => return s,
+        let remote = match choose_remote(&remotes, &self.remote_name) {
+            Some(r) => r,
+            None => {
+                println!("Error: remote {} not found", &self.remote_name);
+                return "".to_string();
+            }
+        };
+        match remote_url_to_link(&remote.url) {
+            Ok(s) => s,
             Err(e) => {
                 println!("{}", e);
-                return "".to_string();
+                "".to_string()
             }
         }
     }
 }
 
 impl AppObject {
-    /// private function. Use public workspace_or_project_count_lines().
-    fn one_project_count_lines(project_path: &Path) -> LinesOfCode {
-        let mut lines_of_code = LinesOfCode::default();
-
-        // src folder
-        let files = unwrap!(traverse_dir_with_exclude_dir(
-            &project_path.join("src"),
-            "/*.rs",
-            // avoid big folders and other folders with *.crev
-            &vec![
-                "/.git".to_string(),
-                "/target".to_string(),
-                "/docs".to_string()
-            ]
-        ));
-        // println!("{:#?}", files);
-        for rs_file_name in files.iter() {
-            //dbg!(&rs_file_name);
-            // Open the file in read-only mode (ignoring errors).
-            let file = File::open(rs_file_name).unwrap();
-            let reader = BufReader::new(file);
-            let mut is_unit_test = false;
-            // Read the file line by line using the lines() iterator from std::io::BufRead.
-            for line in reader.lines() {
-                let line = line.unwrap(); // Ignore errors.
-                let line = line.trim_start();
-                if line.starts_with("///") || line.starts_with("//!") {
-                    lines_of_code.src_doc_comment_lines += 1;
-                } else if line.starts_with("//") || line.starts_with("/!") {
-                    lines_of_code.src_comment_lines += 1;
-                } else if line.starts_with("#[cfg(test)]") {
-                    is_unit_test = true;
-                } else if is_unit_test == true {
-                    lines_of_code.tests_lines += 1;
-                } else {
-                    lines_of_code.src_code_lines += 1;
+    /// Returns the lines of the working tree for the workspace, every member and every file.
+    /// The settings of the members are applied to their files.
+    pub fn count_report(&self) -> anyhow::Result<CountReport> {
+        let current_dir = self.file_system.current_dir()?;
+        println!(
+            "current_dir: {}",
+            Yellow.paint(current_dir.to_string_lossy())
+        );
+
+        // all the files of all the members are read and classified in one pool of threads
+        let project_files = self.workspace_or_project_files()?;
+        // only the changed files are classified again
+        let cache_path = Path::new(FILE_CACHE_FILE);
+        let cache = if self.no_cache {
+            FileCache::default()
+        } else {
+            FileCache::read(self.file_system.as_ref(), cache_path)
+        };
+        // a file deleted after the listing or not readable is an error, not a panic
+        let entries = parallel_map(&project_files, self.jobs, |project_file| {
+            let project_path = current_dir.join(&project_file.member);
+            cache
+                .count_file(
+                    self.file_system.as_ref(),
+                    &project_file.path,
+                    &relative_path(&project_path, &project_file.path),
+                    project_file.file_kind,
+                )
+                .map_err(|e| {
+                    anyhow::anyhow!("Error: {}: {}", project_file.path.to_string_lossy(), e)
+                })
+        })
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()?;
+        if !self.no_cache {
+            let mut new_cache = FileCache {
+                files: project_files
+                    .iter()
+                    .map(|f| f.path.to_string_lossy().into_owned())
+                    .zip(entries.iter().cloned())
+                    .collect(),
+                ..Default::default()
+            };
+            if new_cache.files != cache.files {
+                if let Err(e) = new_cache.write(self.file_system.as_ref(), cache_path) {
+                    println!("{}", e);
                 }
             }
         }
-        // tests folder
-        let files = unwrap!(traverse_dir_with_exclude_dir(
-            &project_path.join("tests"),
-            "/*.rs",
-            // avoid big folders and other folders with *.crev
-            &vec![
-                "/.git".to_string(),
-                "/target".to_string(),
-                "/docs".to_string()
-            ]
-        ));
-        // println!("{:#?}", files);
-        for rs_file_name in files.iter() {
-            //dbg!(&rs_file_name);
-            // Open the file in read-only mode (ignoring errors).
-            let file = File::open(rs_file_name).unwrap();
-            let reader = BufReader::new(file);
-            // Read the file line by line using the lines() iterator from std::io::BufRead.
-            for _line in reader.lines() {
-                lines_of_code.tests_lines += 1;
-            }
-        }
+        let counted: Vec<CountedFile> = entries.into_iter().map(|e| e.counted).collect();
 
-        // examples folder
-        let files = unwrap!(traverse_dir_with_exclude_dir(
-            &project_path.join("examples"),
-            "/*.rs",
-            // avoid big folders and other folders with *.crev
-            &vec![
-                "/.git".to_string(),
-                "/target".to_string(),
-                "/docs".to_string()
-            ]
-        ));
-        for rs_file_name in files.iter() {
-            //dbg!(&rs_file_name);
-            // Open the file in read-only mode (ignoring errors).
-            let file = File::open(rs_file_name).unwrap();
-            let reader = BufReader::new(file);
-            // Read the file line by line using the lines() iterator from std::io::BufRead.
-            for _line in reader.lines().enumerate() {
-                lines_of_code.examples_lines += 1;
+        // cargo toml contains the list of projects
+        let members = self.workspace_members()?;
+        let members = if members.is_empty() {
+            vec!["".to_string()]
+        } else {
+            members
+        };
+        let mut report = CountReport::default();
+        for member in members.iter() {
+            if !member.is_empty() {
+                println!("{}", &member);
             }
+            let (member_paths, member_files): (Vec<&PathBuf>, Vec<&CountedFile>) = project_files
+                .iter()
+                .zip(counted.iter())
+                .filter(|(project_file, _)| &project_file.member == member)
+                .map(|(project_file, counted_file)| (&project_file.path, counted_file))
+                .unzip();
+            let file_lines = project_file_lines(&member_files, self.member_file_patterns(member));
+            let mut member_lines = LinesOfCode::default();
+            for (path, lines_of_code) in member_paths.into_iter().zip(file_lines) {
+                let lines_of_code = self.member_lines(member, lines_of_code);
+                member_lines += lines_of_code.clone();
+                report.files.push(FileCount {
+                    member: member.clone(),
+                    path: relative_path(&current_dir, path),
+                    lines_of_code,
+                });
+            }
+            report.total += member_lines.clone();
+            report.members.push(MemberCount {
+                member: member.clone(),
+                lines_of_code: member_lines,
+            });
         }
-        //println!("{:#?}", &lines_of_code);
         // return
-        lines_of_code
+        Ok(report)
     }
-    pub fn git_remote_output(&self) -> anyhow::Result<String> {
-        let output = std::process::Command::new("git")
-            .arg("remote")
-            .arg("-v")
-            .output()?;
-
-        let output = String::from_utf8(output.stdout)?;
-        println!("output: {}", &output);
+    /// Returns the rs files of one project with the kind: src, tests and examples.
+    /// The files are filtered with the file_filter: all, respect ignore rules or only tracked.
+    pub fn one_project_files(
+        &self,
+        project_path: &Path,
+    ) -> anyhow::Result<Vec<(PathBuf, FileKind)>> {
+        let current_dir = self.file_system.current_dir()?;
+        let file_patterns = self.member_file_patterns(&relative_path(&current_dir, project_path));
+        let mut project_files = vec![];
+        for file_kind in FileKind::all().iter() {
+            let files = traverse_dir_in(
+                self.file_system.as_ref(),
+                &project_path.join(file_kind.folder_name()),
+                "/*.rs",
+                // avoid big folders and other folders with *.crev
+                &exclude_dirs(),
+                !self.no_follow_symlinks,
+            )?;
+            let mut files = filter_files(
+                self.file_system.as_ref(),
+                files,
+                project_path,
+                self.file_filter,
+            )?
+            .into_iter()
+            .filter(|f| file_patterns.is_counted(&relative_path(project_path, f)))
+            .collect::<Vec<PathBuf>>();
+            files.sort();
+            // println!("{:#?}", files);
+            project_files.extend(files.into_iter().map(|f| (f, *file_kind)));
+        }
         // return
-        Ok(output)
+        Ok(project_files)
     }
-    /// returns a Result.
-    /// in the case of error the calling fn will return empty string.
-    pub fn regex_capture(&self, output: String) -> anyhow::Result<String> {
-        // on Github actions they don't use ssh, but https, I need to check that also
-        // I test my regex on https://regex101.com/
-        // regex capture 3 groups: website, user_name and repo_name
-        // "origin  git@github.com:LucianoBestia/lmake_lines_of_code.git (fetch)"
-        // origin    https://github.com/LucianoBestia/lmake_lines_of_code (fetch)
-        println!("{}", &output);
-        let reg = Regex::new(
-            r#"origin\s*(?:https://)?(?:git@)?([^:/]*?)[:/]([^/]*?)/([^. ]*?)(?:\.git)?\s*\(fetch\)"#,
-        )?;
-        let cap = reg
-            .captures(&output)
-            .ok_or(anyhow::anyhow!("Error: reg.captures is None"))?;
-        // dbg!(&cap);
-
-        // indexing can panic, but I would like it to Error
-        anyhow::ensure!(
-            cap.len() == 4,
-            "Error: cap len is not 4, because there are 4 capture groups in regex."
-        );
-        return Ok(format!("https://{}/{}/{}/", &cap[1], &cap[2], &cap[3]));
+    /// Returns the workspace members from Cargo.toml. Empty for a single project.
+    /// A missing or invalid Cargo.toml is an error.
+    pub fn workspace_members(&self) -> anyhow::Result<Vec<String>> {
+        let cargo_toml = self
+            .file_system
+            .read_to_string(Path::new("Cargo.toml"))
+            .map_err(|e| anyhow::anyhow!("Error: Cargo.toml: {}", e))?;
+        let cargo_toml: CargoToml =
+            toml::from_str(&cargo_toml).map_err(|e| anyhow::anyhow!("Error: Cargo.toml: {}", e))?;
+        // return
+        Ok(cargo_toml
+            .workspace
+            .map(|workspace| workspace.members)
+            .unwrap_or_default())
+    }
+    /// Returns all the rs files that are counted in the workspace or single project.
+    pub fn workspace_or_project_files(&self) -> anyhow::Result<Vec<ProjectFile>> {
+        let current_dir = self.file_system.current_dir()?;
+        let members = self.workspace_members()?;
+        let members = if members.is_empty() {
+            vec!["".to_string()]
+        } else {
+            members
+        };
+        let mut project_files = vec![];
+        for member in members.iter() {
+            for (path, file_kind) in self.one_project_files(&current_dir.join(member))? {
+                project_files.push(ProjectFile {
+                    member: member.clone(),
+                    path,
+                    file_kind,
+                });
+            }
+        }
+        // return
+        Ok(project_files)
     }
     /// Returns a string with the code for a markdown table with count of lines.
     ///
@@ -238,7 +369,7 @@ impl AppObject {
     /// ```
     /// use lmake_lines_of_code::*;
     ///
-    /// let app = AppObject{};
+    /// let app = AppObject::new();
     /// let v = app.workspace_or_project_count_lines();
     /// let badges = app.to_string_as_md_table(&v);
     ///
@@ -248,17 +379,20 @@ impl AppObject {
         // I added an empty row to have the next row with different color from the header.
         format!(
             "
-| src code | doc comments | comments | examples | tests |
-| :------: | :----------: | :------: | :------: | :---: |
-|  lines   |     lines    |   lines  |   lines  | lines |
-| {:^8   } | {:^12      } | {:^8   } | {:^8   } | {:^5} |
+| src code | doc comments | comments | examples | tests | generated |
+| :------: | :----------: | :------: | :------: | :---: | :-------: |
+|  lines   |     lines    |   lines  |   lines  | lines |   lines   |
+| {:^8   } | {:^12      } | {:^8   } | {:^8   } | {:^5} | {:^9    } |
 
+{}
 ",
-            v.src_code_lines,
-            v.src_doc_comment_lines,
-            v.src_comment_lines,
-            v.examples_lines,
-            v.tests_lines
+            self.number_format.format(v.src_code_lines),
+            self.number_format.format(v.src_doc_comment_lines),
+            self.number_format.format(v.src_comment_lines),
+            self.number_format.format(v.examples_lines),
+            self.number_format.format(v.tests_lines),
+            self.number_format.format(v.generated_lines),
+            ratios_to_md_table(v)
         )
     }
     /// Returns a string with the markdown code for 4 shield badges.
@@ -271,40 +405,368 @@ impl AppObject {
     /// ```
     /// use lmake_lines_of_code::*;
     ///
-    /// let app = AppObject{};
+    /// let app = AppObject::new();
     /// let v = app.workspace_or_project_count_lines();
     /// let badges = app.to_string_as_shield_badges(&v,"");
     ///
     /// println!("{}", badges);
     /// ```
     pub fn to_string_as_shield_badges(&self, v: &LinesOfCode, link: &str) -> String {
+        self.to_string_as_shield_badges_with_links(v, &BadgeLinks::same(link))
+    }
+    /// Returns a string with the markdown code for 5 shield badges.
+    /// Every badge can have a different link.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let v = LinesOfCode::new(1, 2, 3, 4, 5);
+    /// let mut links = BadgeLinks::same("https://github.com/user/repo/");
+    /// links.tests = "https://github.com/user/repo/tree/master/tests".to_string();
+    /// let badges = app.to_string_as_shield_badges_with_links(&v, &links);
+    ///
+    /// println!("{}", badges);
+    /// ```
+    pub fn to_string_as_shield_badges_with_links(
+        &self,
+        v: &LinesOfCode,
+        links: &BadgeLinks,
+    ) -> String {
         println!("to_string_as_shield_badges() start");
 
-        let src_code_lines = format!(
-            "[![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-{}-green.svg)]({})",
-            v.src_code_lines, link
-        );
-        let src_doc_comment_lines = format!("[![Lines in Doc comments](https://img.shields.io/badge/Lines_in_Doc_comments-{}-blue.svg)]({})",v.src_doc_comment_lines,link);
-        let src_comment_lines = format!(
-        "[![Lines in Comments](https://img.shields.io/badge/Lines_in_comments-{}-purple.svg)]({})",
-        v.src_comment_lines, link
-    );
-        let example_lines = format!(
-        "[![Lines in examples](https://img.shields.io/badge/Lines_in_examples-{}-yellow.svg)]({})",
-        v.examples_lines, link
-    );
-        let tests_lines = format!(
-            "[![Lines in tests](https://img.shields.io/badge/Lines_in_tests-{}-orange.svg)]({})",
-            v.tests_lines, link
-        );
+        // the shields.io style is a query parameter of the image
+        let style = if self.badge_style.is_empty() {
+            "".to_string()
+        } else {
+            format!("?style={}", self.badge_style)
+        };
+        let badge = |alt: &str, label: &str, value: usize, color: &str, link: &str| {
+            format!(
+                "[![{}](https://img.shields.io/badge/{}-{}-{}.svg{})]({})",
+                alt,
+                label,
+                self.number_format.format_for_badge(value),
+                color,
+                style,
+                link
+            )
+        };
+        let badges = [
+            (
+                "code",
+                badge(
+                    "Lines in Rust code",
+                    "Lines_in_Rust",
+                    v.src_code_lines,
+                    "green",
+                    &links.src_code,
+                ),
+            ),
+            (
+                "doc_comments",
+                badge(
+                    "Lines in Doc comments",
+                    "Lines_in_Doc_comments",
+                    v.src_doc_comment_lines,
+                    "blue",
+                    &links.doc_comments,
+                ),
+            ),
+            (
+                "comments",
+                badge(
+                    "Lines in Comments",
+                    "Lines_in_comments",
+                    v.src_comment_lines,
+                    "purple",
+                    &links.comments,
+                ),
+            ),
+            (
+                "examples",
+                badge(
+                    "Lines in examples",
+                    "Lines_in_examples",
+                    v.examples_lines,
+                    "yellow",
+                    &links.examples,
+                ),
+            ),
+            (
+                "tests",
+                badge(
+                    "Lines in tests",
+                    "Lines_in_tests",
+                    v.tests_lines,
+                    "orange",
+                    &links.tests,
+                ),
+            ),
+        ];
+        let mut text = String::new();
+        for (name, badge) in badges.iter() {
+            if self.categories.is_empty() || self.categories.iter().any(|c| c == name) {
+                text.push_str(badge);
+                text.push('\n');
+            }
+        }
+        for ratio in self.ratio_badges.ratios.iter() {
+            let value = v.ratio(*ratio);
+            let link = match ratio {
+                Ratio::DocCommentsPerCode => &links.doc_comments,
+                Ratio::CommentDensity => &links.comments,
+                Ratio::TestsToCode => &links.tests,
+                Ratio::ExamplesToCode => &links.examples,
+            };
+            text.push_str(&format!(
+                "[![{}](https://img.shields.io/badge/{}-{:.2}-{}.svg{})]({})\n",
+                ratio.label(),
+                ratio.label().replace(' ', "_"),
+                value,
+                self.ratio_badges.thresholds(*ratio).color(value),
+                style,
+                link
+            ));
+        }
         //return
-        format!(
-            "{}\n{}\n{}\n{}\n{}\n",
-            src_code_lines, src_doc_comment_lines, src_comment_lines, example_lines, tests_lines
-        )
+        text
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The category of one line. It is the field of LinesOfCode where the line is counted.
+pub enum LineCategory {
+    SrcCode,
+    SrcDocComment,
+    SrcComment,
+    Tests,
+    Examples,
+    /// all lines of a generated file
+    Generated,
+    /// the line #[cfg(test)] is not counted
+    NotCounted,
+}
+
+/// Classifies the lines of one rs file, one after the other.
+/// For src files the doc comments, comments and unit tests are recognized.
+/// Tests and examples files count all lines.
+pub struct LineClassifier {
+    file_kind: FileKind,
+    is_unit_test: bool,
+}
+
+impl LineClassifier {
+    pub fn new(file_kind: FileKind) -> LineClassifier {
+        LineClassifier {
+            file_kind,
+            is_unit_test: false,
+        }
+    }
+    /// Returns the category of the next line in the file.
+    pub fn classify(&mut self, line: &str) -> LineCategory {
+        match self.file_kind {
+            FileKind::Tests => LineCategory::Tests,
+            FileKind::Examples => LineCategory::Examples,
+            FileKind::Src => {
+                let line = line.trim_start();
+                if line.starts_with("///") || line.starts_with("//!") {
+                    LineCategory::SrcDocComment
+                } else if line.starts_with("//") || line.starts_with("/!") {
+                    LineCategory::SrcComment
+                } else if line.starts_with("#[cfg(test)]") {
+                    self.is_unit_test = true;
+                    LineCategory::NotCounted
+                } else if self.is_unit_test {
+                    LineCategory::Tests
+                } else {
+                    LineCategory::SrcCode
+                }
+            }
+        }
+    }
+}
+
+/// The byte order mark at the start of some UTF-8 files.
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Splits the file content into lines. The line ends are LF, CRLF or a lone CR.
+/// The UTF-8 BOM is removed. Invalid UTF-8 is decoded lossy with the replacement character.
+/// Returns the lines and true if the content is valid UTF-8.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let (lines, is_utf8) = split_lines_lossy(b"\xEF\xBB\xBF//! doc\r\nfn a() {}\rfn b() {}\n\xFF\n");
+/// assert_eq!(lines, vec!["//! doc", "fn a() {}", "fn b() {}", "\u{FFFD}"]);
+/// assert!(!is_utf8);
+/// ```
+pub fn split_lines_lossy(content: &[u8]) -> (Vec<String>, bool) {
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let is_utf8 = std::str::from_utf8(content).is_ok();
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'\n' => {
+                lines.push(String::from_utf8_lossy(&content[start..i]).into_owned());
+                start = i + 1;
+            }
+            b'\r' => {
+                lines.push(String::from_utf8_lossy(&content[start..i]).into_owned());
+                // CRLF is one line end
+                if content.get(i + 1) == Some(&b'\n') {
+                    i += 1;
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    // the last line without a line end
+    if start < content.len() {
+        lines.push(String::from_utf8_lossy(&content[start..]).into_owned());
+    }
+    (lines, is_utf8)
+}
+
+/// Returns the category for every line of the file content.
+/// All the lines of a file with a generated header are generated lines.
+/// Files with invalid UTF-8 are decoded lossy and don't panic.
+/// A change of these rules needs a bump of CACHE_FORMAT_VERSION.
+pub fn classify_lines(mut reader: impl BufRead, file_kind: FileKind) -> Vec<LineCategory> {
+    let mut content = vec![];
+    if let Err(e) = reader.read_to_end(&mut content) {
+        println!("{}", Yellow.paint(format!("Warning: {}", e)));
+    }
+    let (lines, _is_utf8) = split_lines_lossy(&content);
+    if has_generated_header(&lines) {
+        return vec![LineCategory::Generated; lines.len()];
+    }
+    let mut classifier = LineClassifier::new(file_kind);
+    lines.iter().map(|line| classifier.classify(line)).collect()
+}
+
+/// Counts the lines of one rs file from any reader, not only from files on disk.
+/// The file kind is the folder where the file would be: src, tests or examples.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let reader = std::io::Cursor::new("/// doc\nfn a() {}\n");
+/// let v = count_reader(reader, FileKind::Src);
+/// assert_eq!(v.src_doc_comment_lines, 1);
+/// assert_eq!(v.src_code_lines, 1);
+/// ```
+pub fn count_reader(reader: impl BufRead, file_kind: FileKind) -> LinesOfCode {
+    let mut lines_of_code = LinesOfCode::default();
+    for category in classify_lines(reader, file_kind) {
+        lines_of_code.add_line(category);
+    }
+    // return
+    lines_of_code
+}
+
+/// Counts the lines of rs source code in memory, like a file from a patch.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let v = count_str("// comment\nfn a() {}\n#[cfg(test)]\nmod tests {}\n", FileKind::Src);
+/// assert_eq!(v, LinesOfCode::new(1, 0, 1, 1, 0));
+/// ```
+pub fn count_str(content: &str, file_kind: FileKind) -> LinesOfCode {
+    count_reader(content.as_bytes(), file_kind)
+}
+
+/// Returns the workspace member and the kind of the rs file from the path relative to the project root.
+/// For a single project the members are empty and the member is an empty string.
+/// Files outside of src, tests and examples, or inside excluded dirs, return None.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let members = vec!["member_a".to_string()];
+/// let (member, file_kind) = member_and_file_kind("member_a/tests/t.rs", &members).unwrap();
+/// assert_eq!(member, "member_a");
+/// assert_eq!(file_kind, FileKind::Tests);
+/// assert!(member_and_file_kind("member_a/build.rs", &members).is_none());
+/// ```
+pub fn member_and_file_kind(path: &str, members: &[String]) -> Option<(String, FileKind)> {
+    if !path.ends_with(".rs") {
+        return None;
+    }
+    let (member, project_path) = if members.is_empty() {
+        ("", path)
+    } else {
+        members.iter().find_map(|member| {
+            path.strip_prefix(member.trim_end_matches('/'))
+                .and_then(|p| p.strip_prefix('/'))
+                .map(|p| (member.as_str(), p))
+        })?
+    };
+    let (folder, relative_path) = project_path.split_once('/')?;
+    let file_kind = FileKind::all()
+        .iter()
+        .copied()
+        .find(|k| k.folder_name() == folder)?;
+    // the last part is the file name
+    let mut dirs: Vec<&str> = relative_path.split('/').collect();
+    dirs.pop();
+    let exclude_dirs = exclude_dirs();
+    if dirs.iter().any(|dir| {
+        exclude_dirs
+            .iter()
+            .any(|excl| excl.trim_start_matches('/') == *dir)
+    }) {
+        return None;
+    }
+    Some((member.to_string(), file_kind))
+}
+
+impl std::ops::AddAssign for LinesOfCode {
+    fn add_assign(&mut self, other: LinesOfCode) {
+        self.src_code_lines += other.src_code_lines;
+        self.src_doc_comment_lines += other.src_doc_comment_lines;
+        self.src_comment_lines += other.src_comment_lines;
+        self.tests_lines += other.tests_lines;
+        self.examples_lines += other.examples_lines;
+        self.generated_lines += other.generated_lines;
+    }
+}
+
 impl LinesOfCode {
+    /// Adds one line to the field of the category.
+    pub fn add_line(&mut self, category: LineCategory) {
+        match category {
+            LineCategory::SrcCode => self.src_code_lines += 1,
+            LineCategory::SrcDocComment => self.src_doc_comment_lines += 1,
+            LineCategory::SrcComment => self.src_comment_lines += 1,
+            LineCategory::Tests => self.tests_lines += 1,
+            LineCategory::Examples => self.examples_lines += 1,
+            LineCategory::Generated => self.generated_lines += 1,
+            LineCategory::NotCounted => {}
+        }
+    }
+    /// The counts in the order of the markdown table:
+    /// src code, doc comments, comments, examples, tests, generated.
+    pub fn as_array(&self) -> [usize; 6] {
+        [
+            self.src_code_lines,
+            self.src_doc_comment_lines,
+            self.src_comment_lines,
+            self.examples_lines,
+            self.tests_lines,
+            self.generated_lines,
+        ]
+    }
     pub fn new(
         src_code_lines: usize,
         src_doc_comment_lines: usize,
@@ -318,6 +780,7 @@ impl LinesOfCode {
             src_comment_lines,
             tests_lines,
             examples_lines,
+            generated_lines: 0,
         }
     }
 }
@@ -0,0 +1,521 @@
+// diff_mod.rs
+//! LOC delta between two git revisions.
+//!
+//! Every line of the changed files is classified at both revisions.
+//! The added and removed lines from `git diff` are counted in the category of that line,
+//! so the report can say "tests grew by 300 lines, docs by 40".
+//! The files are filtered and classified like counting: file patterns, generated files and member settings.
+
+use crate::count_lines_mod::*;
+use crate::generated_mod::include_targets;
+use crate::git_rev_mod::*;
+use crate::AppObject;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Added and removed lines for every category.
+pub struct LinesOfCodeDelta {
+    pub added: LinesOfCode,
+    pub removed: LinesOfCode,
+}
+
+impl LinesOfCodeDelta {
+    /// Net change for every category in the order:
+    /// src code, doc comments, comments, examples, tests, generated.
+    pub fn net(&self) -> [i64; 6] {
+        let a = self.added.as_array();
+        let r = self.removed.as_array();
+        let mut net = [0i64; 6];
+        for i in 0..6 {
+            net[i] = a[i] as i64 - r[i] as i64;
+        }
+        net
+    }
+}
+
+impl std::ops::AddAssign<&LinesOfCodeDelta> for LinesOfCodeDelta {
+    fn add_assign(&mut self, other: &LinesOfCodeDelta) {
+        self.added += other.added.clone();
+        self.removed += other.removed.clone();
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// The delta between two revisions: overall, per workspace member and per file.
+pub struct RevisionsDelta {
+    pub from_rev: String,
+    pub to_rev: String,
+    pub total: LinesOfCodeDelta,
+    /// key is the member path. Empty string for a single project.
+    pub per_member: BTreeMap<String, LinesOfCodeDelta>,
+    /// key is the file path relative to the project root
+    pub per_file: BTreeMap<String, LinesOfCodeDelta>,
+}
+
+/// One changed file from `git diff -U0`: the paths and the changed line numbers (1-based).
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    /// None for a new file
+    pub old_path: Option<String>,
+    /// None for a deleted file
+    pub new_path: Option<String>,
+    pub removed_lines: Vec<usize>,
+    pub added_lines: Vec<usize>,
+    /// the blob object ids from the line `index old..new`, with `--full-index`
+    pub old_oid: Option<String>,
+    pub new_oid: Option<String>,
+}
+
+/// The options of `git diff -U0` that do not depend on the git config of the user:
+/// no external diff tool, no colors and the prefixes `a/` and `b/` even with `diff.mnemonicPrefix` or `diff.noprefix`.
+pub(crate) const GIT_DIFF_U0_ARGS: [&str; 7] = [
+    "diff",
+    "--no-ext-diff",
+    "--no-color",
+    "--src-prefix=a/",
+    "--dst-prefix=b/",
+    "--relative",
+    "-U0",
+];
+
+/// Splits the range `from..to` into two revisions. Without .. the second revision is HEAD.
+pub fn split_rev_range(range: &str) -> (String, String) {
+    match range.split_once("..") {
+        Some((from, to)) if !to.is_empty() => (from.to_string(), to.to_string()),
+        Some((from, _)) => (from.to_string(), "HEAD".to_string()),
+        None => (range.to_string(), "HEAD".to_string()),
+    }
+}
+
+impl AppObject {
+    /// Returns the delta of lines between two revisions, like `v1.0.0..HEAD`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let delta = app.diff_revisions("HEAD..HEAD").unwrap();
+    /// println!("{}", delta_to_md_tables(&delta));
+    /// ```
+    pub fn diff_revisions(&self, range: &str) -> anyhow::Result<RevisionsDelta> {
+        let (from_rev, to_rev) = split_rev_range(range);
+        self.diff_between(&from_rev, &to_rev)
+    }
+    /// Returns the delta of lines between two revisions with git in the project root.
+    /// Every line of the changed files is classified at both revisions.
+    pub fn diff_between(&self, from_rev: &str, to_rev: &str) -> anyhow::Result<RevisionsDelta> {
+        let old_entries = git_ls_tree(&self.project_root, from_rev)?;
+        let new_entries = git_ls_tree(&self.project_root, to_rev)?;
+        let mut args = GIT_DIFF_U0_ARGS.to_vec();
+        args.extend([from_rev, to_rev]);
+        let output = git_output(&self.project_root, &args)?;
+        self.delta_from_git_diff(from_rev, to_rev, &old_entries, &new_entries, &output)
+    }
+    /// Returns the delta of lines from the output of `git diff -U0`.
+    /// The old and new entries are the files of both sides of the diff, to read and classify them.
+    /// The revision `index` means the git index.
+    pub fn delta_from_git_diff(
+        &self,
+        from_rev: &str,
+        to_rev: &str,
+        old_entries: &[GitTreeEntry],
+        new_entries: &[GitTreeEntry],
+        diff_output: &str,
+    ) -> anyhow::Result<RevisionsDelta> {
+        let mut members = workspace_members_in_tree(&self.project_root, old_entries)?;
+        for member in workspace_members_in_tree(&self.project_root, new_entries)? {
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+        let file_diffs = parse_diff_u0(diff_output);
+
+        let mut delta = RevisionsDelta {
+            from_rev: from_rev.to_string(),
+            to_rev: to_rev.to_string(),
+            ..Default::default()
+        };
+        if file_diffs.is_empty() {
+            return Ok(delta);
+        }
+        let old_included = self.included_at_rev(from_rev, old_entries, &members)?;
+        let new_included = self.included_at_rev(to_rev, new_entries, &members)?;
+        let old_side = DiffSide {
+            oids: oids_by_path(old_entries),
+            included: &old_included,
+        };
+        let new_side = DiffSide {
+            oids: oids_by_path(new_entries),
+            included: &new_included,
+        };
+        self.add_file_diffs(&mut delta, &file_diffs, &old_side, &new_side, &members)?;
+        // return
+        Ok(delta)
+    }
+    /// Adds the changed lines of the file diffs to the delta, in the category of that line.
+    /// The blobs of both sides are read with one `git cat-file --batch` process.
+    pub(crate) fn add_file_diffs(
+        &self,
+        delta: &mut RevisionsDelta,
+        file_diffs: &[FileDiff],
+        old_side: &DiffSide,
+        new_side: &DiffSide,
+        members: &[String],
+    ) -> anyhow::Result<()> {
+        let old_files: Vec<Option<DiffFile>> = file_diffs
+            .iter()
+            .map(|d| self.diff_file(old_side, d.old_path.as_deref(), members))
+            .collect();
+        let new_files: Vec<Option<DiffFile>> = file_diffs
+            .iter()
+            .map(|d| self.diff_file(new_side, d.new_path.as_deref(), members))
+            .collect();
+        let [old_contents, new_contents] =
+            read_diff_files(&self.project_root, [&old_files, &new_files])?;
+        for (i, file_diff) in file_diffs.iter().enumerate() {
+            let mut file_delta = LinesOfCodeDelta::default();
+            let mut file_member = None;
+            if let (Some(file), Some(content)) = (&old_files[i], old_contents.get(&i)) {
+                file_delta.removed =
+                    self.count_diff_file(file, content, old_side, &file_diff.removed_lines);
+                file_member = Some(file.member.clone());
+            }
+            if let (Some(file), Some(content)) = (&new_files[i], new_contents.get(&i)) {
+                file_delta.added =
+                    self.count_diff_file(file, content, new_side, &file_diff.added_lines);
+                file_member = Some(file.member.clone());
+            }
+            if let Some(member) = file_member {
+                let path = file_diff
+                    .new_path
+                    .clone()
+                    .or_else(|| file_diff.old_path.clone())
+                    .unwrap_or_default();
+                delta.total += &file_delta;
+                *delta.per_member.entry(member).or_default() += &file_delta;
+                *delta.per_file.entry(path).or_default() += &file_delta;
+            }
+        }
+        Ok(())
+    }
+    /// Returns how the file of one side of the diff is counted, or None if it is not counted.
+    /// The file patterns and categories of the member apply, like counting the files.
+    fn diff_file(
+        &self,
+        side: &DiffSide,
+        path: Option<&str>,
+        members: &[String],
+    ) -> Option<DiffFile> {
+        let path = path?;
+        let (member, file_kind) = member_and_file_kind(path, members)?;
+        let file_patterns = self.member_file_patterns(&member);
+        let member_path = relative_path(Path::new(&member), Path::new(path));
+        if !file_patterns.is_counted(&member_path) {
+            return None;
+        }
+        // return
+        Some(DiffFile {
+            oid: side.oids.get(path).cloned(),
+            path: path.to_string(),
+            member,
+            file_kind,
+        })
+    }
+    /// Counts the changed lines of one side of the diff in the category of that line.
+    fn count_diff_file(
+        &self,
+        file: &DiffFile,
+        content: &[u8],
+        side: &DiffSide,
+        line_numbers: &[usize],
+    ) -> LinesOfCode {
+        let categories = self.member_line_categories(
+            &file.member,
+            &file.path,
+            file.file_kind,
+            content,
+            side.included,
+        );
+        let mut changed = LinesOfCode::default();
+        count_changed_lines(&mut changed, &categories, line_numbers);
+        // return
+        self.member_lines(&file.member, changed)
+    }
+    /// Returns the category of every line of a file of the member, like counting.
+    /// The files that match a generated pattern of the member or are include! targets
+    /// have only generated lines. The path is relative to the project root.
+    /// The categories of the member are applied later with member_lines().
+    pub(crate) fn member_line_categories(
+        &self,
+        member: &str,
+        path: &str,
+        file_kind: FileKind,
+        content: &[u8],
+        included: &HashSet<String>,
+    ) -> Vec<LineCategory> {
+        let member_path = relative_path(Path::new(member), Path::new(path));
+        let categories = classify_lines(content, file_kind);
+        if self.member_file_patterns(member).is_generated(&member_path) || included.contains(path) {
+            vec![LineCategory::Generated; categories.len()]
+        } else {
+            categories
+        }
+    }
+    /// Returns the include! targets of the counted files at the revision.
+    /// Only the files with `include!` are read, they are found with `git grep`.
+    pub(crate) fn included_at_rev(
+        &self,
+        rev: &str,
+        entries: &[GitTreeEntry],
+        members: &[String],
+    ) -> anyhow::Result<HashSet<String>> {
+        let args = if rev == "index" {
+            vec![
+                "grep", "--cached", "-l", "-z", "-F", "include!", "--", "*.rs",
+            ]
+        } else {
+            vec!["grep", "-l", "-z", "-F", "include!", rev, "--", "*.rs"]
+        };
+        // git grep fails without matches
+        let output = git_output(&self.project_root, &args).unwrap_or_default();
+        let prefix = format!("{}:", rev);
+        let with_include: HashSet<&str> = output
+            .split('\0')
+            .map(|path| path.strip_prefix(&prefix).unwrap_or(path))
+            .collect();
+        let includers: Vec<&GitTreeEntry> = entries
+            .iter()
+            .filter(|e| with_include.contains(e.path.as_str()))
+            .filter(|e| match member_and_file_kind(&e.path, members) {
+                Some((member, _)) => self
+                    .member_file_patterns(&member)
+                    .is_counted(&relative_path(Path::new(&member), Path::new(&e.path))),
+                None => false,
+            })
+            .collect();
+        let oids: Vec<String> = includers.iter().map(|e| e.oid.clone()).collect();
+        let mut included = HashSet::new();
+        for (e, content) in includers
+            .iter()
+            .zip(git_cat_file_batch(&self.project_root, &oids)?)
+        {
+            included.extend(include_targets(&e.path, &String::from_utf8_lossy(&content)));
+        }
+        // return
+        Ok(included)
+    }
+}
+
+/// The files of one side of the diff and the include! targets at that revision.
+pub(crate) struct DiffSide<'a> {
+    /// the blob object id by path
+    pub(crate) oids: HashMap<String, String>,
+    pub(crate) included: &'a HashSet<String>,
+}
+
+/// A counted file of one side of the diff.
+struct DiffFile {
+    path: String,
+    oid: Option<String>,
+    member: String,
+    file_kind: FileKind,
+}
+
+fn oids_by_path(entries: &[GitTreeEntry]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|e| (e.path.clone(), e.oid.clone()))
+        .collect()
+}
+
+/// Reads the counted files of both sides with one `git cat-file --batch` process.
+/// The key is the index of the file diff.
+fn read_diff_files(
+    project_root: &Path,
+    sides: [&[Option<DiffFile>]; 2],
+) -> anyhow::Result<[HashMap<usize, Vec<u8>>; 2]> {
+    let mut keys = vec![];
+    let mut oids = vec![];
+    for (side, files) in sides.iter().enumerate() {
+        for (i, file) in files.iter().enumerate() {
+            if let Some(file) = file {
+                let oid = file.oid.clone().ok_or_else(|| {
+                    anyhow::anyhow!("Error: {} is not in the git tree", file.path)
+                })?;
+                keys.push((side, i));
+                oids.push(oid);
+            }
+        }
+    }
+    let mut contents = [HashMap::new(), HashMap::new()];
+    for ((side, i), content) in keys
+        .into_iter()
+        .zip(git_cat_file_batch(project_root, &oids)?)
+    {
+        contents[side].insert(i, content);
+    }
+    // return
+    Ok(contents)
+}
+
+/// Counts the changed lines (1-based line numbers) in the category of that line.
+pub fn count_changed_lines(
+    lines_of_code: &mut LinesOfCode,
+    categories: &[LineCategory],
+    line_numbers: &[usize],
+) {
+    for line_number in line_numbers.iter() {
+        if let Some(category) = categories.get(line_number.wrapping_sub(1)) {
+            lines_of_code.add_line(*category);
+        }
+    }
+}
+
+/// Parses the output of `git diff -U0` into changed files with changed line numbers.
+/// The hunk header is `@@ -old_start,old_count +new_start,new_count @@`.
+pub fn parse_diff_u0(output: &str) -> Vec<FileDiff> {
+    let mut file_diffs: Vec<FileDiff> = vec![];
+    // a removed line "-- x" looks like a header "--- x", but headers are only before the hunks
+    let mut is_header = false;
+    for line in output.lines() {
+        if line.starts_with("diff --git ") {
+            file_diffs.push(FileDiff::default());
+            is_header = true;
+        } else if let Some(file_diff) = file_diffs.last_mut() {
+            if let Some(oids) = line.strip_prefix("index ").filter(|_| is_header) {
+                // index old..new or index old..new mode
+                let oids = oids.split(' ').next().unwrap_or("");
+                if let Some((old_oid, new_oid)) = oids.split_once("..") {
+                    file_diff.old_oid = Some(old_oid.to_string());
+                    file_diff.new_oid = Some(new_oid.to_string());
+                }
+            } else if is_header && line.starts_with("--- ") {
+                file_diff.old_path = diff_path(&line[4..], "a/");
+            } else if is_header && line.starts_with("+++ ") {
+                file_diff.new_path = diff_path(&line[4..], "b/");
+            } else if let Some(hunk) = line.strip_prefix("@@ ") {
+                is_header = false;
+                let mut ranges = hunk.split(' ');
+                if let (Some(old), Some(new)) = (ranges.next(), ranges.next()) {
+                    file_diff
+                        .removed_lines
+                        .extend(hunk_line_numbers(old.trim_start_matches('-')));
+                    file_diff
+                        .added_lines
+                        .extend(hunk_line_numbers(new.trim_start_matches('+')));
+                }
+            }
+        }
+    }
+    // return
+    file_diffs
+}
+
+/// The path after --- or +++. /dev/null means the file does not exist.
+fn diff_path(path: &str, prefix: &str) -> Option<String> {
+    if path == "/dev/null" {
+        None
+    } else {
+        Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+    }
+}
+
+/// The range `start,count` or just `start` for count 1.
+fn hunk_line_numbers(range: &str) -> std::ops::Range<usize> {
+    let (start, count) = match range.split_once(',') {
+        Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+        None => (range.parse().unwrap_or(0), 1),
+    };
+    start..start + count
+}
+
+/// Names of the categories in the order of LinesOfCode::as_array().
+pub const CATEGORY_NAMES: [&str; 6] = [
+    "src code",
+    "doc comments",
+    "comments",
+    "examples",
+    "tests",
+    "generated",
+];
+
+/// Returns the markdown tables for the delta: overall, per member and per file.
+pub fn delta_to_md_tables(delta: &RevisionsDelta) -> String {
+    let mut text = format!(
+        "\n## Lines of code {}..{}\n\n",
+        delta.from_rev, delta.to_rev
+    );
+    text.push_str("| category | added | removed | net |\n| :------- | ----: | ------: | --: |\n");
+    let added = delta.total.added.as_array();
+    let removed = delta.total.removed.as_array();
+    let net = delta.total.net();
+    for i in 0..6 {
+        text.push_str(&format!(
+            "| {} | {} | {} | {:+} |\n",
+            CATEGORY_NAMES[i], added[i], removed[i], net[i]
+        ));
+    }
+    if delta.per_member.keys().any(|member| !member.is_empty()) {
+        text.push_str(&delta_table("member", &delta.per_member));
+    }
+    text.push_str(&delta_table("file", &delta.per_file));
+    // return
+    text
+}
+
+/// One row for every key with the added, removed and net lines of every category.
+fn delta_table(key_name: &str, rows: &BTreeMap<String, LinesOfCodeDelta>) -> String {
+    let mut text = format!(
+        "\n| {} | src code | doc comments | comments | examples | tests | generated |\n| :--- | ---: | ---: | ---: | ---: | ---: | ---: |\n",
+        key_name
+    );
+    for (key, row) in rows.iter() {
+        text.push_str(&format!("| {} |{}\n", key, delta_cells(row)));
+    }
+    // return
+    text
+}
+
+/// Table cells `+added -removed (net)` for every category.
+fn delta_cells(delta: &LinesOfCodeDelta) -> String {
+    let added = delta.added.as_array();
+    let removed = delta.removed.as_array();
+    delta
+        .net()
+        .iter()
+        .enumerate()
+        .map(|(i, net)| format!(" +{} -{} ({:+}) |", added[i], removed[i], net))
+        .collect()
+}
+
+/// Returns one sentence for the changelog, like:
+/// `Lines of code v1.0.0..HEAD: src code grew by 120 lines, tests grew by 300 lines, doc comments shrank by 4 lines.`
+pub fn delta_to_changelog_text(delta: &RevisionsDelta) -> String {
+    let parts: Vec<String> = delta
+        .total
+        .net()
+        .iter()
+        .zip(CATEGORY_NAMES.iter())
+        .filter(|(net, _)| **net != 0)
+        .map(|(net, name)| {
+            if *net > 0 {
+                format!("{} grew by {} lines", name, net)
+            } else {
+                format!("{} shrank by {} lines", name, -net)
+            }
+        })
+        .collect();
+    let changes = if parts.is_empty() {
+        "no change".to_string()
+    } else {
+        parts.join(", ")
+    };
+    format!(
+        "Lines of code {}..{}: {}.",
+        delta.from_rev, delta.to_rev, changes
+    )
+}
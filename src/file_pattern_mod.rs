@@ -0,0 +1,96 @@
+// file_pattern_mod.rs
+//! Include and exclude glob patterns for the counted files.
+//!
+//! The patterns are matched against the path relative to the project root,
+//! like `src/generated/**` or `**/*_pb.rs`.
+//! They come from the CLI, the config files and the `lmake_lines_of_code` metadata in Cargo.toml.
+
+use crate::count_lines_mod::*;
+
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+
+/// `*` does not match `/`, only `**` matches many folders.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Include and exclude patterns.
+/// Without include patterns all files are included.
+/// The exclude patterns win over the include patterns.
+pub struct FilePatterns {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    /// the matching files are counted in generated_lines
+    pub generated: Vec<Pattern>,
+}
+
+impl FilePatterns {
+    /// Adds one include pattern.
+    pub fn add_include(&mut self, pattern: &str) -> anyhow::Result<()> {
+        self.include.push(parse_pattern(pattern)?);
+        Ok(())
+    }
+    /// Adds one exclude pattern.
+    pub fn add_exclude(&mut self, pattern: &str) -> anyhow::Result<()> {
+        self.exclude.push(parse_pattern(pattern)?);
+        Ok(())
+    }
+    /// Adds one pattern for generated files.
+    pub fn add_generated(&mut self, pattern: &str) -> anyhow::Result<()> {
+        self.generated.push(parse_pattern(pattern)?);
+        Ok(())
+    }
+    /// Returns true if the file is counted.
+    /// The path is relative to the project root, with / as separator.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let mut file_patterns = FilePatterns::default();
+    /// file_patterns.add_exclude("**/*_pb.rs").unwrap();
+    /// assert!(file_patterns.is_counted("src/lib.rs"));
+    /// assert!(!file_patterns.is_counted("src/proto/msg_pb.rs"));
+    /// ```
+    pub fn is_counted(&self, relative_path: &str) -> bool {
+        let matches = |p: &Pattern| p.matches_with(relative_path, MATCH_OPTIONS);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+    /// Returns true if the file matches a pattern for generated files.
+    pub fn is_generated(&self, relative_path: &str) -> bool {
+        self.generated
+            .iter()
+            .any(|p| p.matches_with(relative_path, MATCH_OPTIONS))
+    }
+}
+
+fn parse_pattern(pattern: &str) -> anyhow::Result<Pattern> {
+    Pattern::new(pattern.trim_start_matches("./"))
+        .map_err(|e| anyhow::anyhow!("Error: glob pattern {}: {}", pattern, e))
+}
+
+/// Returns the markdown table with every counted file and its kind: src, tests or examples.
+/// The paths are relative to the base directory, usually the project root.
+pub fn project_files_to_md_table(project_files: &[ProjectFile], base_dir: &Path) -> String {
+    let mut table = String::from(
+        "
+| file | kind | member |
+| :--- | :--- | :----- |
+",
+    );
+    for f in project_files.iter() {
+        table.push_str(&format!(
+            "| {} | {} | {} |\n",
+            f.path.strip_prefix(base_dir).unwrap_or(&f.path).display(),
+            f.file_kind.folder_name(),
+            f.member
+        ));
+    }
+    // return
+    table
+}
@@ -0,0 +1,273 @@
+// file_system_mod.rs
+//! The file system used for counting, traversal, the cache and README writing.
+//!
+//! The real implementation uses std::fs.
+//! The in-memory implementation can hold archives, git trees or test fixtures,
+//! so the whole pipeline runs without touching the disk.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use unwrap::unwrap;
+
+#[derive(Debug, Clone, PartialEq)]
+/// One entry of a directory.
+pub struct DirEntryInfo {
+    pub path: PathBuf,
+    /// the entry itself is a directory, not a symlink to a directory
+    pub is_dir: bool,
+    pub is_symlink: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The metadata of a file or directory. Symlinks are followed.
+pub struct FileMetadata {
+    pub is_dir: bool,
+    pub len: u64,
+    /// modification time in nanoseconds since the unix epoch
+    pub modified: u64,
+    /// identifies the directory to avoid loops: device and inode
+    pub id: (u64, u64),
+}
+
+/// The file system operations used by this crate.
+/// Relative paths are relative to the current_dir(), the project root.
+pub trait FileSystem: Send + Sync {
+    /// The folder of the project, where Cargo.toml is.
+    fn current_dir(&self) -> io::Result<PathBuf>;
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<DirEntryInfo>>;
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Writes the file and creates the parent folders.
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()>;
+    /// Makes the file executable, like a git hook. Without file permissions it does nothing.
+    fn set_executable(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).map(|m| m.is_dir).unwrap_or(false)
+    }
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Default for Box<dyn FileSystem> {
+    fn default() -> Self {
+        Box::new(RealFileSystem::default())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// The file system on disk with std::fs.
+/// Relative paths are relative to the root, not to the current directory of the process.
+pub struct RealFileSystem {
+    /// the project root. Empty means the current directory of the process.
+    pub root: PathBuf,
+}
+
+impl RealFileSystem {
+    /// The file system with relative paths from the project root.
+    pub fn new(root: &Path) -> RealFileSystem {
+        RealFileSystem {
+            root: root.to_path_buf(),
+        }
+    }
+    fn absolute(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() || self.root.as_os_str().is_empty() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        }
+    }
+}
+
+impl FileSystem for RealFileSystem {
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        let current_dir = std::env::current_dir()?;
+        if self.root.as_os_str().is_empty() {
+            return Ok(current_dir);
+        }
+        // return
+        Ok(current_dir.join(&self.root))
+    }
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<DirEntryInfo>> {
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(self.absolute(dir))? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            entries.push(DirEntryInfo {
+                path: entry.path(),
+                is_dir: file_type.is_dir(),
+                is_symlink: file_type.is_symlink(),
+            });
+        }
+        Ok(entries)
+    }
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let path = self.absolute(path);
+        let metadata = std::fs::metadata(&path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Ok(FileMetadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified,
+            id: file_id(&path, &metadata)?,
+        })
+    }
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(self.absolute(path))
+    }
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        let path = self.absolute(path);
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(path, content)
+    }
+    #[cfg(unix)]
+    fn set_executable(&self, path: &Path) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(self.absolute(path), std::fs::Permissions::from_mode(0o755))
+    }
+}
+
+/// The device and inode of the file.
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &std::fs::Metadata) -> io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+/// Without inodes the hash of the canonical path identifies the file.
+#[cfg(not(unix))]
+fn file_id(path: &Path, _metadata: &std::fs::Metadata) -> io::Result<(u64, u64)> {
+    let canonical = path.canonicalize()?;
+    Ok((0, crate::fnv1a_hash(canonical.to_string_lossy().as_bytes())))
+}
+
+/// The file system in memory. The folders exist implicitly for every file.
+/// The modification time is a counter that grows on every write.
+pub struct MemoryFileSystem {
+    root: PathBuf,
+    files: Mutex<BTreeMap<PathBuf, (u64, Vec<u8>)>>,
+}
+
+impl MemoryFileSystem {
+    /// An empty file system. The root is the current_dir for relative paths.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// use std::path::Path;
+    /// let file_system = MemoryFileSystem::new("/project");
+    /// file_system.write(Path::new("src/lib.rs"), b"fn a() {}\n").unwrap();
+    /// assert!(file_system.is_dir(Path::new("/project/src")));
+    /// assert_eq!(file_system.read(Path::new("/project/src/lib.rs")).unwrap(), b"fn a() {}\n");
+    /// ```
+    pub fn new(root: &str) -> MemoryFileSystem {
+        MemoryFileSystem {
+            root: PathBuf::from(root),
+            files: Mutex::new(BTreeMap::new()),
+        }
+    }
+    /// Adds a file, like write(), but in a builder style.
+    pub fn with_file(self, path: &str, content: &str) -> MemoryFileSystem {
+        unwrap!(self.write(Path::new(path), content.as_bytes()));
+        self
+    }
+    fn absolute(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        }
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        Ok(self.root.clone())
+    }
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<DirEntryInfo>> {
+        let dir = self.absolute(dir);
+        let files = unwrap!(self.files.lock());
+        let mut entries: Vec<DirEntryInfo> = vec![];
+        for path in files.keys() {
+            if let Ok(rest) = path.strip_prefix(&dir) {
+                let mut components = rest.components();
+                if let Some(first) = components.next() {
+                    let entry = DirEntryInfo {
+                        path: dir.join(first),
+                        is_dir: components.next().is_some(),
+                        is_symlink: false,
+                    };
+                    if !entries.contains(&entry) {
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+        if entries.is_empty() && !files.contains_key(&dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", dir.display()),
+            ));
+        }
+        Ok(entries)
+    }
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let path = self.absolute(path);
+        let files = unwrap!(self.files.lock());
+        let id = (0, crate::fnv1a_hash(path.to_string_lossy().as_bytes()));
+        if let Some((modified, content)) = files.get(&path) {
+            return Ok(FileMetadata {
+                is_dir: false,
+                len: content.len() as u64,
+                modified: *modified,
+                id,
+            });
+        }
+        if files.keys().any(|p| p.starts_with(&path)) {
+            return Ok(FileMetadata {
+                is_dir: true,
+                len: 0,
+                modified: 0,
+                id,
+            });
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        ))
+    }
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let path = self.absolute(path);
+        unwrap!(self.files.lock())
+            .get(&path)
+            .map(|(_, content)| content.clone())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} not found", path.display()),
+                )
+            })
+    }
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        let path = self.absolute(path);
+        let mut files = unwrap!(self.files.lock());
+        let modified = files.values().map(|(m, _)| *m).max().unwrap_or(0) + 1;
+        files.insert(path, (modified, content.to_vec()));
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+// generated_mod.rs
+//! Detects generated code, so it is counted in generated_lines and not as hand-written code.
+//!
+//! A file is generated if it has a header marker like `@generated` in the leading comments,
+//! if it matches a `generated` path pattern or if it is the target of `include!("file.rs")`.
+//! Files included from `OUT_DIR` are in the target folder and are never counted.
+
+use crate::count_lines_mod::*;
+use crate::file_pattern_mod::FilePatterns;
+use crate::utilsmod::parallel_map;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Yellow};
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use unwrap::unwrap;
+
+/// Only the first lines of the file are searched for the markers.
+pub const GENERATED_HEADER_LINES: usize = 10;
+
+/// Markers in the header comments of generated files (lowercase).
+/// prost and tonic write `@generated`, bindgen writes `automatically generated by rust-bindgen`.
+/// A plain "do not edit" is not a marker, hand-written files use it too.
+pub const GENERATED_MARKERS: [&str; 2] = ["@generated", "automatically generated by"];
+
+/// Returns true if the leading comments of the file contain a generated marker.
+/// The leading comments end with the first line of code, like `use` or an attribute.
+/// Doc comments are not searched, because they can describe the markers.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let lines = vec!["// This file is @generated by prost-build.".to_string(), "pub struct A {}".to_string()];
+/// assert!(has_generated_header(&lines));
+/// let lines = vec!["pub struct A {}".to_string(), "// @generated".to_string()];
+/// assert!(!has_generated_header(&lines));
+/// ```
+pub fn has_generated_header(lines: &[String]) -> bool {
+    // inside a block comment and if it is a doc comment
+    let mut block: Option<bool> = None;
+    for line in lines.iter().take(GENERATED_HEADER_LINES) {
+        let line = line.trim();
+        let is_doc = match block {
+            Some(is_doc) => {
+                if line.contains("*/") {
+                    block = None;
+                }
+                is_doc
+            }
+            None if line.is_empty() => continue,
+            None if line.starts_with("///") || line.starts_with("//!") => true,
+            None if line.starts_with("//") => false,
+            None if line.starts_with("/*") => {
+                let is_doc = line.starts_with("/**") || line.starts_with("/*!");
+                if !line[2..].contains("*/") {
+                    block = Some(is_doc);
+                }
+                is_doc
+            }
+            // the first line of code ends the header
+            None => return false,
+        };
+        if !is_doc {
+            let line = line.to_lowercase();
+            if GENERATED_MARKERS.iter().any(|marker| line.contains(marker)) {
+                return true;
+            }
+        }
+    }
+    // return
+    false
+}
+
+/// Returns the targets of `include!("path")` in the file content.
+/// The targets are relative to the project root like the file path, with / as separator.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let targets = include_targets("src/proto/mod.rs", r#"include!("../generated/msg.rs");"#);
+/// assert_eq!(targets, vec!["src/generated/msg.rs".to_string()]);
+/// // comments and doc comments are not code
+/// assert!(include_targets("src/lib.rs", r#"/// include!("a.rs");"#).is_empty());
+/// ```
+pub fn include_targets(relative_path: &str, content: &str) -> Vec<String> {
+    // the path in include! is relative to the folder of the file
+    let dir = match relative_path.rfind('/') {
+        Some(pos) => &relative_path[..pos],
+        None => "",
+    };
+    // include! with a string literal. include_str! and include_bytes! are not code.
+    let reg = unwrap!(Regex::new(r#"\binclude!\s*\(\s*"([^"]+)"\s*\)"#));
+    let mut targets = vec![];
+    let mut in_block_comment = false;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if in_block_comment {
+            in_block_comment = !line.contains("*/");
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix("/*") {
+            in_block_comment = !comment.contains("*/");
+            continue;
+        }
+        // the code before a line comment
+        let code = match line.find("//") {
+            Some(pos) => &line[..pos],
+            None => line,
+        };
+        targets.extend(
+            reg.captures_iter(code)
+                .map(|cap| normalize_path(&format!("{}/{}", dir, &cap[1]))),
+        );
+    }
+    // return
+    targets
+}
+
+/// Removes the . and .. parts of the path.
+fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = vec![];
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The count of lines of one file, before the generated patterns and include! targets are applied.
+pub(crate) struct CountedFile {
+    /// path relative to the project root
+    pub(crate) relative_path: String,
+    pub(crate) include_targets: Vec<String>,
+    pub(crate) lines_of_code: LinesOfCode,
+    /// all lines, if the file is generated
+    pub(crate) line_count: usize,
+    /// the file is not valid UTF-8 and was decoded lossy
+    #[serde(default)]
+    pub(crate) is_lossy: bool,
+}
+
+/// Classifies the lines of one file. The path is relative to the project root.
+pub(crate) fn count_one_file(
+    relative_path: &str,
+    file_kind: FileKind,
+    content: &[u8],
+) -> CountedFile {
+    let lines_of_code = count_reader(content, file_kind);
+    let (lines, is_utf8) = split_lines_lossy(content);
+    let line_count = lines.len();
+    CountedFile {
+        relative_path: relative_path.to_string(),
+        include_targets: include_targets(relative_path, &String::from_utf8_lossy(content)),
+        lines_of_code,
+        line_count,
+        is_lossy: !is_utf8,
+    }
+}
+
+/// Returns the lines of every counted file of one project. Prints a warning for files with invalid UTF-8.
+/// The files that match a generated pattern or are include! targets are counted in generated_lines.
+pub(crate) fn project_file_lines(
+    files: &[&CountedFile],
+    file_patterns: &FilePatterns,
+) -> Vec<LinesOfCode> {
+    let included: HashSet<&String> = files
+        .iter()
+        .flat_map(|f| f.include_targets.iter())
+        .collect();
+    let mut file_lines = vec![];
+    for f in files.iter() {
+        if f.is_lossy {
+            println!(
+                "{}",
+                Yellow.paint(format!(
+                    "Warning: {} is not valid UTF-8, the lines are counted with a lossy decode.",
+                    f.relative_path
+                ))
+            );
+        }
+        if file_patterns.is_generated(&f.relative_path) || included.contains(&f.relative_path) {
+            file_lines.push(LinesOfCode {
+                generated_lines: f.line_count,
+                ..LinesOfCode::default()
+            });
+        } else {
+            file_lines.push(f.lines_of_code.clone());
+        }
+    }
+    // return
+    file_lines
+}
+
+/// Sums the counted files of one project, like project_file_lines().
+pub(crate) fn sum_project_files(
+    files: &[&CountedFile],
+    file_patterns: &FilePatterns,
+) -> LinesOfCode {
+    let mut lines_of_code = LinesOfCode::default();
+    for file_lines in project_file_lines(files, file_patterns) {
+        lines_of_code += file_lines;
+    }
+    // return
+    lines_of_code
+}
+
+/// Counts the lines of the files of one project with a pool of threads.
+/// Every file is (path relative to the project root, kind, content).
+/// The generated files are counted in generated_lines.
+pub(crate) fn count_project_contents(
+    files: &[(String, FileKind, Vec<u8>)],
+    file_patterns: &FilePatterns,
+    jobs: usize,
+) -> LinesOfCode {
+    let counted = parallel_map(files, jobs, |(path, file_kind, content)| {
+        count_one_file(path, *file_kind, content)
+    });
+    let counted: Vec<&CountedFile> = counted.iter().collect();
+    // return
+    sum_project_files(&counted, file_patterns)
+}
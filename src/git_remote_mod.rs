@@ -0,0 +1,227 @@
+// git_remote_mod.rs
+//! Finds the git remote repository url for the link of the badges.
+//!
+//! The remotes are read directly from the .git/config file, so no git binary is required.
+//! Only if that fails, the app spawns `git remote -v`.
+
+use crate::file_system_mod::FileSystem;
+use crate::git_rev_mod::git_command;
+use crate::AppObject;
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use unwrap::unwrap;
+
+/// The remote names are tried in this order, if the remote name is not defined.
+/// If none of them exists, the first remote is used.
+pub const REMOTE_FALLBACK_ORDER: [&str; 2] = ["upstream", "origin"];
+
+#[derive(Debug, Clone, PartialEq)]
+/// One git remote: the name and the fetch url.
+pub struct GitRemote {
+    pub name: String,
+    pub url: String,
+}
+
+impl AppObject {
+    /// Returns the output string after $ git remote -v.
+    pub fn git_remote_output(&self) -> anyhow::Result<String> {
+        let output = git_command(&self.project_root)
+            .arg("remote")
+            .arg("-v")
+            .output()?;
+
+        let output = String::from_utf8(output.stdout)?;
+        println!("output: {}", &output);
+        // return
+        Ok(output)
+    }
+    /// returns a Result.
+    /// in the case of error the calling fn will return empty string.
+    /// The remote is chosen with the fallback order: upstream, origin, first remote.
+    pub fn regex_capture(&self, output: String) -> anyhow::Result<String> {
+        println!("{}", &output);
+        let remotes = parse_git_remote_v(&output);
+        let remote = choose_remote(&remotes, "")
+            .ok_or_else(|| anyhow::anyhow!("Error: no remote in git remote -v"))?;
+        remote_url_to_link(&remote.url)
+    }
+    /// Returns the list of remotes.
+    /// First reads the .git/config file (also for worktrees and submodules).
+    /// If that fails or has no remotes, spawns `git remote -v`.
+    /// The remotes can also come from included config files, that are not parsed here.
+    pub fn git_remotes(&self) -> anyhow::Result<Vec<GitRemote>> {
+        let project_dir = self.file_system.current_dir()?;
+        match git_config_remotes(self.file_system.as_ref(), &project_dir) {
+            Ok(remotes) if !remotes.is_empty() => return Ok(remotes),
+            Ok(_) => println!("no remote in .git/config"),
+            Err(e) => println!("{}", e),
+        }
+        let output = self.git_remote_output()?;
+        let remotes = parse_git_remote_v(&output);
+        if remotes.is_empty() {
+            anyhow::bail!("Error: no git remote");
+        }
+        // return
+        Ok(remotes)
+    }
+}
+
+/// Finds the git directory for the project directory or any of its parents.
+/// The `.git` can be a directory or a file with `gitdir: path` (worktrees and submodules).
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let file_system = MemoryFileSystem::new("/project").with_file(".git/HEAD", "ref: refs/heads/main\n");
+/// let git_dir = find_git_dir(&file_system, std::path::Path::new("/project/src")).unwrap();
+/// assert_eq!(git_dir, std::path::Path::new("/project/.git"));
+/// ```
+pub fn find_git_dir(file_system: &dyn FileSystem, project_dir: &Path) -> anyhow::Result<PathBuf> {
+    for dir in project_dir.ancestors() {
+        let dot_git = dir.join(".git");
+        let metadata = match file_system.metadata(&dot_git) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir {
+            return Ok(dot_git);
+        } else {
+            let content = file_system.read_to_string(&dot_git)?;
+            let git_dir = content
+                .lines()
+                .find_map(|line| line.strip_prefix("gitdir:"))
+                .ok_or_else(|| anyhow::anyhow!("Error: no gitdir: in {}", dot_git.display()))?;
+            // the path can be relative to the folder of the .git file
+            return Ok(dir.join(git_dir.trim()));
+        }
+    }
+    anyhow::bail!("Error: no .git in {} or parents", project_dir.display())
+}
+
+/// Returns the git directory shared by all worktrees.
+/// A worktree git directory has the `commondir` file that points to the main git directory.
+pub fn git_common_dir(file_system: &dyn FileSystem, git_dir: &Path) -> PathBuf {
+    match file_system.read_to_string(&git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Returns the remotes from the .git/config file without spawning git.
+/// The config of a worktree is in the common git directory.
+pub fn git_config_remotes(
+    file_system: &dyn FileSystem,
+    project_dir: &Path,
+) -> anyhow::Result<Vec<GitRemote>> {
+    let git_dir = find_git_dir(file_system, project_dir)?;
+    let config =
+        file_system.read_to_string(&git_common_dir(file_system, &git_dir).join("config"))?;
+    // return
+    Ok(parse_git_config_remotes(&config))
+}
+
+/// Parses the remote sections of a git config file.
+/// `[remote "origin"]` followed by `url = git@github.com:user/repo.git`
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let remotes = parse_git_config_remotes("[remote \"origin\"]\n\turl = git@github.com:user/repo.git\n");
+/// assert_eq!(remotes[0].name, "origin");
+/// assert_eq!(remotes[0].url, "git@github.com:user/repo.git");
+/// ```
+pub fn parse_git_config_remotes(config: &str) -> Vec<GitRemote> {
+    let reg_section = unwrap!(Regex::new(r#"^\[\s*remote\s+"([^"]*)"\s*\]"#));
+    let mut remotes: Vec<GitRemote> = vec![];
+    let mut remote_name: Option<String> = None;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            remote_name = reg_section.captures(line).map(|cap| cap[1].to_string());
+        } else if let Some(name) = &remote_name {
+            if let Some((key, value)) = line.split_once('=') {
+                // only the first url is the fetch url
+                if key.trim().eq_ignore_ascii_case("url")
+                    && !remotes.iter().any(|r| &r.name == name)
+                {
+                    remotes.push(GitRemote {
+                        name: name.clone(),
+                        url: value.trim().trim_matches('"').to_string(),
+                    });
+                }
+            }
+        }
+    }
+    // return
+    remotes
+}
+
+/// Parses the output of `git remote -v`. Only the (fetch) lines are used.
+pub fn parse_git_remote_v(output: &str) -> Vec<GitRemote> {
+    output
+        .lines()
+        .filter(|line| line.trim_end().ends_with("(fetch)"))
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(name), Some(url)) => Some(GitRemote {
+                    name: name.to_string(),
+                    url: url.to_string(),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Chooses the remote by name.
+/// If the name is empty, uses the fallback order: upstream, origin, first remote.
+pub fn choose_remote<'a>(remotes: &'a [GitRemote], remote_name: &str) -> Option<&'a GitRemote> {
+    if !remote_name.is_empty() {
+        return remotes.iter().find(|r| r.name == remote_name);
+    }
+    REMOTE_FALLBACK_ORDER
+        .iter()
+        .find_map(|name| remotes.iter().find(|r| &r.name == name))
+        .or_else(|| remotes.first())
+}
+
+/// Converts the remote url (ssh or https) to the https link for badges.
+/// Local remotes like `file:///srv/repo.git` or `../repo` have no link and return an error.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let link = remote_url_to_link("git@github.com:LucianoBestia/lmake_lines_of_code.git").unwrap();
+/// assert_eq!(link, "https://github.com/LucianoBestia/lmake_lines_of_code/");
+/// assert!(remote_url_to_link("file:///srv/git/repo.git").is_err());
+/// ```
+pub fn remote_url_to_link(url: &str) -> anyhow::Result<String> {
+    // on Github actions they don't use ssh, but https, I need to check that also
+    // I test my regex on https://regex101.com/
+    // regex capture 2 groups: website and path (user_name/repo_name)
+    // git@github.com:LucianoBestia/lmake_lines_of_code.git
+    // https://github.com/LucianoBestia/lmake_lines_of_code
+    // ssh://git@github.com:22/LucianoBestia/lmake_lines_of_code.git
+    let reg_url = Regex::new(
+        r#"^(?:https?|ssh|git)://(?:[^@/]+@)?([^:/]+)(?::\d+)?/+([^/]+/.+?)(?:\.git)?/*$"#,
+    )?;
+    // the scp-like syntax without a scheme needs the colon after the host.
+    // Without it, it is a local path. A single letter before the colon is a Windows drive.
+    let reg_scp = Regex::new(r#"^(?:[^@/]+@)?([^:/\\]{2,}):/*([^/]+/.+?)(?:\.git)?/*$"#)?;
+    let url = url.trim();
+    let reg = if url.contains("://") {
+        reg_url
+    } else {
+        reg_scp
+    };
+    let cap = reg
+        .captures(url)
+        .ok_or_else(|| anyhow::anyhow!("Error: remote url {} is not a link", url))?;
+    // return
+    Ok(format!("https://{}/{}/", &cap[1], &cap[2]))
+}
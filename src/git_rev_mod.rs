@@ -0,0 +1,303 @@
+// git_rev_mod.rs
+//! Counts lines at any git revision (commit, tag, branch) without checking it out.
+//!
+//! The files are read from the git object database with `git ls-tree` and `git cat-file --batch`.
+//! Uncommitted files in the working tree are never counted.
+
+use crate::count_lines_mod::*;
+use crate::file_pattern_mod::FilePatterns;
+use crate::file_system_mod::FileSystem;
+use crate::generated_mod::count_project_contents;
+use crate::git_remote_mod::{find_git_dir, git_common_dir};
+use crate::AppObject;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Yellow};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq)]
+/// One file entry from `git ls-tree`.
+pub struct GitTreeEntry {
+    /// object id of the blob
+    pub oid: String,
+    /// path relative to the project root
+    pub path: String,
+}
+
+impl AppObject {
+    /// Returns the struct LinesOfCode for the files at the git revision.
+    /// Automatically detects if this is a workspace or single rust project,
+    /// from the Cargo.toml at that revision.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let v = app.workspace_or_project_count_lines_at_rev("HEAD");
+    /// dbg!(&v);
+    /// ```
+    pub fn workspace_or_project_count_lines_at_rev(
+        &self,
+        rev: &str,
+    ) -> anyhow::Result<LinesOfCode> {
+        println!("git revision: {}", Yellow.paint(rev));
+        let entries = git_ls_tree(&self.project_root, rev)?;
+        anyhow::ensure!(
+            entries.iter().any(|e| e.path == "Cargo.toml"),
+            "Error: no Cargo.toml at revision {}",
+            rev
+        );
+        let mut members = workspace_members_in_tree(&self.project_root, &entries)?;
+        if members.is_empty() {
+            members.push("".to_string());
+        }
+
+        let mut lines_of_code = LinesOfCode::default();
+        for member in members.iter() {
+            if !member.is_empty() {
+                println!("{}", &member);
+            }
+            let v = count_lines_in_tree_entries(
+                &self.project_root,
+                &entries,
+                member,
+                self.member_file_patterns(member),
+                self.jobs,
+            )?;
+            lines_of_code += self.member_lines(member, v);
+        }
+        // return
+        Ok(lines_of_code)
+    }
+    /// Returns the commit of the git revision.
+    /// Error if the revision is not a commit, like a typo in `--rev`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// assert!(app.verify_rev("no_such_revision").is_err());
+    /// ```
+    pub fn verify_rev(&self, rev: &str) -> anyhow::Result<String> {
+        let commit = format!("{}^{{commit}}", rev);
+        git_output(
+            &self.project_root,
+            &["rev-parse", "--verify", "--quiet", &commit],
+        )
+        .map(|commit| commit.trim().to_string())
+        .map_err(|_| anyhow::anyhow!("Error: {} is not a git revision", rev))
+    }
+}
+
+/// Returns the workspace members from the Cargo.toml in the tree. Empty for a single project.
+/// The blobs are read with git in the project root.
+pub fn workspace_members_in_tree(
+    project_root: &Path,
+    entries: &[GitTreeEntry],
+) -> anyhow::Result<Vec<String>> {
+    let cargo_toml_entry = match entries.iter().find(|e| e.path == "Cargo.toml") {
+        Some(e) => e,
+        None => return Ok(vec![]),
+    };
+    let cargo_toml =
+        git_cat_file_batch(project_root, std::slice::from_ref(&cargo_toml_entry.oid))?.remove(0);
+    let cargo_toml: CargoToml = toml::from_str(&String::from_utf8_lossy(&cargo_toml))?;
+    // return
+    Ok(cargo_toml
+        .workspace
+        .map(|workspace| workspace.members)
+        .unwrap_or_default())
+}
+
+/// Counts the lines of one project from the tree entries.
+/// The project path is relative to the project root. Empty for a single project.
+/// The file patterns are matched against the path relative to the project.
+/// The lines are classified with a pool of jobs threads, 0 means one thread per CPU.
+pub fn count_lines_in_tree_entries(
+    project_root: &Path,
+    entries: &[GitTreeEntry],
+    project_path: &str,
+    file_patterns: &FilePatterns,
+    jobs: usize,
+) -> anyhow::Result<LinesOfCode> {
+    let members: Vec<String> = if project_path.is_empty() {
+        vec![]
+    } else {
+        vec![project_path.to_string()]
+    };
+    let mut files = vec![];
+    for file_kind in FileKind::all().iter() {
+        let kind_entries: Vec<&GitTreeEntry> = entries
+            .iter()
+            .filter(|e| {
+                member_and_file_kind(&e.path, &members).map(|(_, kind)| kind) == Some(*file_kind)
+                    && file_patterns
+                        .is_counted(&relative_path(Path::new(project_path), Path::new(&e.path)))
+            })
+            .collect();
+        let oids: Vec<String> = kind_entries.iter().map(|e| e.oid.clone()).collect();
+        for (e, content) in kind_entries
+            .iter()
+            .zip(git_cat_file_batch(project_root, &oids)?)
+        {
+            files.push((
+                relative_path(Path::new(project_path), Path::new(&e.path)),
+                *file_kind,
+                content,
+            ));
+        }
+    }
+    // return
+    Ok(count_project_contents(&files, file_patterns, jobs))
+}
+
+/// The git command that runs in the directory with `git -C`.
+/// An empty directory is the current directory of the process.
+pub(crate) fn git_command(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    if !dir.as_os_str().is_empty() {
+        command.arg("-C").arg(dir);
+    }
+    command
+}
+
+/// Runs git in the directory and returns the stdout. Error if git fails.
+pub(crate) fn git_output(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = git_command(dir).args(args).output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "Error: git {}: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // the output can contain the content of files with invalid UTF-8
+    // return
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the commit of HEAD from the git directory without spawning git.
+/// The branch is looked up in the loose refs and then in packed-refs.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// use std::path::Path;
+/// let file_system = MemoryFileSystem::new("/project")
+///     .with_file(".git/HEAD", "ref: refs/heads/main\n")
+///     .with_file(".git/packed-refs", "# pack-refs\n1a2b3c refs/heads/main\n");
+/// assert_eq!(git_head_commit(&file_system, Path::new("/project")).unwrap(), "1a2b3c");
+/// ```
+pub fn git_head_commit(file_system: &dyn FileSystem, project_dir: &Path) -> anyhow::Result<String> {
+    let git_dir = find_git_dir(file_system, project_dir)?;
+    let head = file_system.read_to_string(&git_dir.join("HEAD"))?;
+    let ref_name = match head.trim().strip_prefix("ref:") {
+        Some(ref_name) => ref_name.trim().to_string(),
+        // detached HEAD
+        None => return Ok(head.trim().to_string()),
+    };
+    // the branches of a worktree are in the common git directory
+    let common_dir = git_common_dir(file_system, &git_dir);
+    for dir in [&git_dir, &common_dir].iter() {
+        if let Ok(commit) = file_system.read_to_string(&dir.join(&ref_name)) {
+            return Ok(commit.trim().to_string());
+        }
+    }
+    let packed_refs = file_system
+        .read_to_string(&common_dir.join("packed-refs"))
+        .unwrap_or_default();
+    packed_refs
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, name)| name.trim() == ref_name)
+        .map(|(commit, _)| commit.to_string())
+        .ok_or_else(|| anyhow::anyhow!("Error: no commit for {}", ref_name))
+}
+
+/// Lists all the files (blobs) at the revision, recursively.
+/// The paths are relative to the project root, like the working tree paths.
+pub fn git_ls_tree(project_root: &Path, rev: &str) -> anyhow::Result<Vec<GitTreeEntry>> {
+    let output = git_output(project_root, &["ls-tree", "-r", "-z", rev])?;
+    let mut entries = vec![];
+    // every entry is: mode SP type SP oid TAB path NUL
+    for entry in output.split('\0').filter(|e| !e.is_empty()) {
+        let (meta, path) = entry
+            .split_once('\t')
+            .ok_or_else(|| anyhow::anyhow!("Error: git ls-tree entry {}", entry))?;
+        let mut meta = meta.split(' ');
+        if let (Some(_mode), Some("blob"), Some(oid)) = (meta.next(), meta.next(), meta.next()) {
+            entries.push(GitTreeEntry {
+                oid: oid.to_string(),
+                path: path.to_string(),
+            });
+        }
+    }
+    // return
+    Ok(entries)
+}
+
+/// Reads the content of the blobs with one `git cat-file --batch` process.
+/// The result is in the same order as the object ids.
+/// On every error the git process is killed, so it never outlives the call.
+pub fn git_cat_file_batch(project_root: &Path, oids: &[String]) -> anyhow::Result<Vec<Vec<u8>>> {
+    if oids.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut child = git_command(project_root)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let (mut stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+        (Some(stdin), Some(stdout)) => (stdin, stdout),
+        _ => {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Error: git cat-file pipes");
+        }
+    };
+    let input = oids.join("\n") + "\n";
+    // write in a thread, so the full stdout pipe cannot block the writing
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let contents = read_cat_file_batch(stdout, oids);
+    if contents.is_err() {
+        // after the kill the writer thread ends with a broken pipe
+        let _ = child.kill();
+    }
+    let written = writer.join();
+    child.wait()?;
+    let contents = contents?;
+    written.map_err(|_| anyhow::anyhow!("Error: git cat-file writer thread"))??;
+    // return
+    Ok(contents)
+}
+
+/// Reads the blobs from the stdout of `git cat-file --batch`.
+fn read_cat_file_batch(stdout: impl Read, oids: &[String]) -> anyhow::Result<Vec<Vec<u8>>> {
+    let mut reader = BufReader::new(stdout);
+    let mut contents = vec![];
+    for oid in oids.iter() {
+        // header: oid SP type SP size LF
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let size: usize = match header.trim_end().rsplit(' ').next() {
+            Some(size) if !header.ends_with("missing\n") => size.parse()?,
+            _ => anyhow::bail!("Error: git cat-file object {} is missing", oid),
+        };
+        let mut content = vec![0; size];
+        reader.read_exact(&mut content)?;
+        // the content is followed by LF
+        let mut lf = [0; 1];
+        reader.read_exact(&mut lf)?;
+        contents.push(content);
+    }
+    // return
+    Ok(contents)
+}
@@ -0,0 +1,236 @@
+// history_mod.rs
+//! LOC history over git tags or commits.
+//!
+//! For every point in history the lines are counted at that revision without checking it out.
+//! The counts are cached by commit hash in target/lmake_lines_of_code/history_cache.json,
+//! so reruns only count the new revisions.
+//! The cache is dropped when the file patterns, the member overrides or the classification rules change.
+
+use crate::cache_mod::{cache_version, fnv1a_hash};
+use crate::count_lines_mod::*;
+use crate::file_system_mod::FileSystem;
+use crate::git_rev_mod::git_output;
+use crate::AppObject;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Yellow};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The cache file for the history. The commit hash is the key.
+pub const HISTORY_CACHE_FILE: &str = "target/lmake_lines_of_code/history_cache.json";
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The cached counts of the commits, valid only for the same settings.
+struct HistoryCache {
+    /// the hash of the settings that change the counts
+    settings: String,
+    commits: BTreeMap<String, LinesOfCode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Which revisions are points in the history.
+pub enum HistorySource {
+    /// all tags sorted by date
+    Tags,
+    /// every Nth commit on the branch (first parent), always with the last commit
+    Commits { branch: String, every: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// One point in the history: revision name, commit, date and the count of lines.
+pub struct HistoryPoint {
+    /// tag name or short commit hash
+    pub rev_name: String,
+    pub commit: String,
+    /// commit date in ISO 8601 format
+    pub date: String,
+    pub lines_of_code: LinesOfCode,
+}
+
+impl AppObject {
+    /// Counts lines at every point in the history.
+    /// The cached counts are used for commits that were already counted.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let source = HistorySource::Commits { branch: "HEAD".to_string(), every: 1 };
+    /// let history = app.history(&source).unwrap();
+    /// println!("{}", history_to_md_table(&history));
+    /// ```
+    pub fn history(&self, source: &HistorySource) -> anyhow::Result<Vec<HistoryPoint>> {
+        let revisions = match source {
+            HistorySource::Tags => git_tags_by_date(&self.project_root)?,
+            HistorySource::Commits { branch, every } => {
+                git_commits_every_nth(&self.project_root, branch, *every)?
+            }
+        };
+        let cache_path = Path::new(HISTORY_CACHE_FILE);
+        let settings = self.history_settings();
+        let mut cache = read_history_cache(self.file_system.as_ref(), cache_path);
+        if cache.settings != settings {
+            cache = HistoryCache {
+                settings,
+                commits: BTreeMap::new(),
+            };
+        }
+        let mut history = vec![];
+        for (rev_name, commit, date) in revisions.into_iter() {
+            let lines_of_code = match cache.commits.get(&commit) {
+                Some(v) => v.clone(),
+                None => {
+                    let v = self.workspace_or_project_count_lines_at_rev(&commit)?;
+                    cache.commits.insert(commit.clone(), v.clone());
+                    v
+                }
+            };
+            history.push(HistoryPoint {
+                rev_name,
+                commit,
+                date,
+                lines_of_code,
+            });
+        }
+        self.file_system
+            .write(cache_path, serde_json::to_string_pretty(&cache)?.as_bytes())?;
+        // return
+        Ok(history)
+    }
+    /// The hash of the settings that change the counts at a revision:
+    /// the version of the rules, the file patterns and the member overrides.
+    fn history_settings(&self) -> String {
+        let settings = format!(
+            "{}\n{:?}\n{:?}",
+            cache_version(),
+            self.file_patterns,
+            self.member_overrides
+        );
+        // return
+        format!("{:016x}", fnv1a_hash(settings.as_bytes()))
+    }
+}
+
+/// Reads the cache. A missing or corrupt cache, or the old format, is just empty.
+fn read_history_cache(file_system: &dyn FileSystem, cache_path: &Path) -> HistoryCache {
+    file_system
+        .read_to_string(cache_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Returns (tag name, commit, date) for all tags sorted by date.
+/// Annotated tags are peeled to the commit.
+pub fn git_tags_by_date(project_root: &Path) -> anyhow::Result<Vec<(String, String, String)>> {
+    let output = git_output(
+        project_root,
+        &[
+        "for-each-ref",
+        "refs/tags",
+        "--sort=creatordate",
+        "--format=%(refname:short)%09%(objectname)%09%(*objectname)%09%(creatordate:iso-strict)",
+    ],
+    )?;
+    let mut tags = vec![];
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if let [name, object, peeled, date] = parts.as_slice() {
+            // lightweight tags point directly to the commit
+            let commit = if peeled.is_empty() { object } else { peeled };
+            tags.push((name.to_string(), commit.to_string(), date.to_string()));
+        }
+    }
+    // return
+    Ok(tags)
+}
+
+/// Returns (short commit, commit, date) for every Nth commit on the branch.
+/// The oldest commit is first. The last commit is always included.
+pub fn git_commits_every_nth(
+    project_root: &Path,
+    branch: &str,
+    every: usize,
+) -> anyhow::Result<Vec<(String, String, String)>> {
+    let every = every.max(1);
+    let output = git_output(
+        project_root,
+        &[
+            "log",
+            "--first-parent",
+            "--reverse",
+            "--format=%h%x09%H%x09%cI",
+            branch,
+        ],
+    )?;
+    let lines: Vec<&str> = output.lines().collect();
+    let mut commits = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if i % every == 0 || i + 1 == lines.len() {
+            let parts: Vec<&str> = line.split('\t').collect();
+            if let [short, commit, date] = parts.as_slice() {
+                commits.push((short.to_string(), commit.to_string(), date.to_string()));
+            }
+        }
+    }
+    // return
+    Ok(commits)
+}
+
+/// Returns the markdown table for the history: one row for every point.
+pub fn history_to_md_table(history: &[HistoryPoint]) -> String {
+    let mut table = String::from(
+        "
+| revision | date | src code | doc comments | comments | examples | tests | generated |
+| :------- | :--- | -------: | -----------: | -------: | -------: | ----: | --------: |
+",
+    );
+    for p in history.iter() {
+        let v = &p.lines_of_code;
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            p.rev_name,
+            // only the day
+            p.date.get(..10).unwrap_or(&p.date),
+            v.src_code_lines,
+            v.src_doc_comment_lines,
+            v.src_comment_lines,
+            v.examples_lines,
+            v.tests_lines,
+            v.generated_lines
+        ));
+    }
+    // return
+    table
+}
+
+/// Returns the CSV text for the history with a header row.
+pub fn history_to_csv(history: &[HistoryPoint]) -> String {
+    let mut csv = String::from("revision,commit,date,src_code_lines,src_doc_comment_lines,src_comment_lines,examples_lines,tests_lines,generated_lines\n");
+    for p in history.iter() {
+        let v = &p.lines_of_code;
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            p.rev_name,
+            p.commit,
+            p.date,
+            v.src_code_lines,
+            v.src_doc_comment_lines,
+            v.src_comment_lines,
+            v.examples_lines,
+            v.tests_lines,
+            v.generated_lines
+        ));
+    }
+    // return
+    csv
+}
+
+/// Returns the JSON text for the history.
+pub fn history_to_json(history: &[HistoryPoint]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(history)?)
+}
@@ -0,0 +1,173 @@
+// ignore_mod.rs
+//! Filters the counted files: only files that are part of the repository.
+//!
+//! The rules from .gitignore, .ignore and .git/info/exclude are respected,
+//! or only the files tracked by git (`git ls-files`) are counted.
+
+use crate::file_system_mod::FileSystem;
+use crate::git_remote_mod::{find_git_dir, git_common_dir};
+use crate::git_rev_mod::git_output;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Which files are counted.
+pub enum FileFilter {
+    /// all rs files, only the excluded dirs are skipped
+    #[default]
+    All,
+    /// skip files that match the rules in .gitignore, .ignore and .git/info/exclude
+    RespectIgnore,
+    /// only files tracked by git
+    Tracked,
+}
+
+impl std::str::FromStr for FileFilter {
+    type Err = anyhow::Error;
+    /// Parses all, respect-ignore or tracked.
+    fn from_str(s: &str) -> anyhow::Result<FileFilter> {
+        match s {
+            "all" => Ok(FileFilter::All),
+            "respect-ignore" => Ok(FileFilter::RespectIgnore),
+            "tracked" => Ok(FileFilter::Tracked),
+            _ => anyhow::bail!(
+                "Error: unknown file filter {}. Use: all, respect-ignore or tracked.",
+                s
+            ),
+        }
+    }
+}
+
+/// The ignore rules for the files under one root directory (the git work tree).
+/// The .gitignore and .ignore files are read lazily for every directory.
+pub struct IgnoreRules<'a> {
+    file_system: &'a dyn FileSystem,
+    root: PathBuf,
+    /// .git/info/exclude has the lowest priority
+    info_exclude: Option<Gitignore>,
+    /// .gitignore and .ignore of one directory, .ignore has priority
+    by_dir: RefCell<HashMap<PathBuf, Vec<Gitignore>>>,
+}
+
+impl<'a> IgnoreRules<'a> {
+    /// Rules for the work tree that contains the project directory.
+    /// Without git, the root is the project directory.
+    pub fn new(file_system: &'a dyn FileSystem, project_dir: &Path) -> IgnoreRules<'a> {
+        // the work tree root is the folder with .git, a directory or a file for worktrees
+        let root = project_dir
+            .ancestors()
+            .find(|dir| file_system.metadata(&dir.join(".git")).is_ok())
+            .unwrap_or(project_dir)
+            .to_path_buf();
+        // a worktree shares info/exclude with the main git directory
+        let info_exclude = find_git_dir(file_system, project_dir)
+            .ok()
+            .and_then(|git_dir| {
+                let exclude = git_common_dir(file_system, &git_dir)
+                    .join("info")
+                    .join("exclude");
+                gitignore_from_file(file_system, &root, &exclude)
+            });
+        IgnoreRules {
+            file_system,
+            root,
+            info_exclude,
+            by_dir: RefCell::new(HashMap::new()),
+        }
+    }
+    /// Returns true if the file is ignored by the rules of any parent directory.
+    /// The rules in deeper directories win over the rules in upper directories.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let dirs: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .collect();
+        // deepest first
+        for dir in dirs.iter() {
+            for matcher in self.matchers_for_dir(dir).iter().rev() {
+                let m = matcher.matched_path_or_any_parents(path, is_dir);
+                if m.is_ignore() {
+                    return true;
+                } else if m.is_whitelist() {
+                    return false;
+                }
+            }
+        }
+        match &self.info_exclude {
+            Some(matcher) => matcher
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore(),
+            None => false,
+        }
+    }
+    fn matchers_for_dir(&self, dir: &Path) -> Vec<Gitignore> {
+        let mut by_dir = self.by_dir.borrow_mut();
+        by_dir
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                [".gitignore", ".ignore"]
+                    .iter()
+                    .filter_map(|name| gitignore_from_file(self.file_system, dir, &dir.join(name)))
+                    .collect()
+            })
+            .clone()
+    }
+}
+
+/// Reads one ignore file. None if the file does not exist.
+/// The patterns are relative to the root folder.
+fn gitignore_from_file(
+    file_system: &dyn FileSystem,
+    root: &Path,
+    file: &Path,
+) -> Option<Gitignore> {
+    let content = file_system.read_to_string(file).ok()?;
+    let mut builder = GitignoreBuilder::new(root);
+    for line in content.lines() {
+        // an invalid pattern is skipped, like git does
+        let _ = builder.add_line(Some(file.to_path_buf()), line);
+    }
+    builder.build().ok()
+}
+
+/// Returns the paths of the files tracked by git under the directory.
+/// The paths start with the directory, like the paths from the traversal.
+pub fn git_tracked_files(dir: &Path) -> anyhow::Result<HashSet<PathBuf>> {
+    let output = git_output(dir, &["ls-files", "-z"])?;
+    // return
+    Ok(output
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(|p| dir.join(p))
+        .collect())
+}
+
+/// Keeps only the files allowed by the filter.
+/// The paths must start with the project directory.
+/// With the tracked filter it is an error if git cannot list the files, instead of counting nothing.
+pub fn filter_files(
+    file_system: &dyn FileSystem,
+    files: Vec<PathBuf>,
+    project_dir: &Path,
+    file_filter: FileFilter,
+) -> anyhow::Result<Vec<PathBuf>> {
+    // return
+    Ok(match file_filter {
+        FileFilter::All => files,
+        FileFilter::RespectIgnore => {
+            let rules = IgnoreRules::new(file_system, project_dir);
+            files
+                .into_iter()
+                .filter(|f| !rules.is_ignored(f, false))
+                .collect()
+        }
+        FileFilter::Tracked => {
+            let tracked = git_tracked_files(project_dir)?;
+            files.into_iter().filter(|f| tracked.contains(f)).collect()
+        }
+    })
+}
@@ -1,14 +1,14 @@
 // region: lmake_md_to_doc_comments include README.md A //!
 //! # lmake_lines_of_code  
 //!
-//! ***version: 1.1.7  date: 2020-08-22 authors: Luciano Bestia***  
+//! ***version: 2.0.0  date: 2026-10-19 authors: Luciano Bestia***  
 //! **Lines of code for Rust projects**
 //!
-//! [![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-379-green.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
-//! [![Lines in Doc comments](https://img.shields.io/badge/Lines_in_Doc_comments-269-blue.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
-//! [![Lines in Comments](https://img.shields.io/badge/Lines_in_comments-56-purple.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-5113-green.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in Doc comments](https://img.shields.io/badge/Lines_in_Doc_comments-1840-blue.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in Comments](https://img.shields.io/badge/Lines_in_comments-202-purple.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
 //! [![Lines in examples](https://img.shields.io/badge/Lines_in_examples-0-yellow.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
-//! [![Lines in tests](https://img.shields.io/badge/Lines_in_tests-142-orange.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
+//! [![Lines in tests](https://img.shields.io/badge/Lines_in_tests-1390-orange.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
 //!
 //! [![crates.io](https://meritbadge.herokuapp.com/lmake_lines_of_code)](https://crates.io/crates/lmake_lines_of_code) [![Documentation](https://docs.rs/lmake_lines_of_code/badge.svg)](https://docs.rs/lmake_lines_of_code/) [![crev reviews](https://web.crev.dev/rust-reviews/badge/crev_count/lmake_lines_of_code.svg)](https://web.crev.dev/rust-reviews/crate/lmake_lines_of_code/) [![Lib.rs](https://img.shields.io/badge/Lib.rs-rust-orange.svg)](https://lib.rs/crates/lmake_lines_of_code/) [![Licence](https://img.shields.io/badge/license-MIT-blue.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/blob/master/LICENSE) [![Rust](https://github.com/LucianoBestia/lmake_lines_of_code/workflows/RustAction/badge.svg)](https://github.com/LucianoBestia/lmake_lines_of_code/)
 //!
@@ -46,10 +46,58 @@
 //!
 //! All other files: md, toml, html, js, ... are not counted.  
 //!
+//! The files are read as bytes. The line ends can be LF, CRLF or a lone CR. The UTF-8 BOM at the start of the file is ignored.\
+//! Files with invalid UTF-8 are counted with a lossy decode and reported as warnings.  
+//! Symlinks are followed. The option `--no-follow-symlinks` skips them. Every folder is visited only once,\
+//! so a symlink to a parent folder does not cause an infinite loop. File names that are not UTF-8 are supported.  
+//!
 //! ### Workspace
 //!
 //! Workspaces have member projects, that are written in cargo.toml.\
 //! The program counts lines of every project and sums them together.  
+//! The files of all the projects are read and classified in parallel with a pool of threads, one thread per CPU.\
+//! The option `--jobs` limits the number of threads. The result does not depend on the order of the threads:  
+//! `lmake_lines_of_code --jobs 4`  
+//! The count of every file is cached in `target/lmake_lines_of_code/cache.json` by path, size, mtime and content hash.\
+//! Only the changed files are classified again. A new version of the tool or of the classification rules drops the cache. The option `--no-cache` counts all the files without the cache:  
+//! `lmake_lines_of_code --no-cache`  
+//!
+//! ### Ignored and untracked files
+//!
+//! By default all the rs files are counted, only some big folders like `target` are skipped.\
+//! The option `--respect-ignore` skips the files that match the rules in `.gitignore`, `.ignore` and `.git/info/exclude`:  
+//! `lmake_lines_of_code --respect-ignore`  
+//! The option `--tracked` counts only the files tracked by git (`git ls-files`):  
+//! `lmake_lines_of_code --tracked`  
+//! If git cannot list the tracked files, `--tracked` is an error instead of counting nothing.  
+//!
+//! ### Include and exclude patterns
+//!
+//! The glob patterns `--include` and `--exclude` are matched against the path relative to the project root:  
+//! `lmake_lines_of_code --exclude "src/generated/**" --exclude "**/*_pb.rs"`  
+//! Without include patterns all files are included. The exclude patterns win over the include patterns.\
+//! The same patterns can be written in Cargo.toml or in the config file (see [Configuration](#configuration)):  
+//!
+//! ```toml
+//! [package.metadata.lmake_lines_of_code]
+//! include = ["src/**", "tests/**"]
+//! exclude = ["src/old/**"]
+//! generated = ["src/proto/**"]
+//! ```
+//!
+//! The option `--list-files` shows every counted file and its kind: src, tests or examples:  
+//! `lmake_lines_of_code --list-files`  
+//!
+//! ### Generated code
+//!
+//! Generated code is counted in `generated_lines` and not as hand-written code. A file is generated if:  
+//!
+//! 1. the comments at the start of the file, before the first line of code, contain `@generated` or `automatically generated by` (prost, tonic, bindgen),  
+//! 2. it matches a `--generated` glob pattern or the `generated` list in the Cargo.toml config,  
+//! 3. it is the target of `include!("file.rs")` in another counted file.  
+//!
+//! Sources included with `include!(concat!(env!("OUT_DIR"), ...))` are in the target folder and are never counted.  
+//! The markdown table and the subcommands show the generated lines in their own column.  
 //!
 //! ## Output
 //!
@@ -57,10 +105,133 @@
 //! If the CLI is called with one argument:  
 //! `lmake_lines_of_code http://website`  
 //! This will be used for the link of all 4 shield badges.  
-//! Else the app will try  
+//! Else the app will read the remote url from the `.git/config` file (also in worktrees), so the git binary is not required.\
+//! If that fails, it will try  
 //! `git remote -v`  
 //! to get the remote url.  
+//! The remote is chosen in this order: `upstream`, `origin`, the first remote.\
+//! Use the option `--remote` to choose another remote:  
+//! `lmake_lines_of_code --remote my_fork`  
+//! If there is no git remote, the link is taken from Cargo.toml in this order: `package.repository`, `package.homepage`, `workspace.package.repository`.  
+//! Every badge can have a different link. The placeholder `{link}` is replaced with the common link:  
+//! `lmake_lines_of_code --badge-link tests={link}tree/master/tests --badge-link examples={link}tree/master/examples`  
+//! The badge names are: code, doc_comments, comments, examples and tests.  
 //! You can copy/paste it into README.md.  
+//! The option `--categories code,tests` shows only some badges and `--badge-style flat-square` sets the shields.io style.\
+//! The option `--output-format json` prints the counts as JSON instead of the markdown table and badges.  
+//!
+//! ### Ratios
+//!
+//! The ratios show the quality at a glance. They are printed in the markdown table under the lines:  
+//!
+//! 1. `doc_comments_per_code`: doc comment lines per code line,  
+//! 2. `comment_density`: doc comments and comments in all the lines of src,  
+//! 3. `tests_to_code`: tests lines per code line,  
+//! 4. `examples_to_code`: examples lines per code line.  
+//!
+//! The option `--ratio-badges tests_to_code,doc_comments_per_code` adds shield badges for the ratios.\
+//! The badge is red below the yellow threshold, yellow below the green threshold and green above.\
+//! The thresholds can be changed for every ratio: `--ratio-threshold tests_to_code=0.3:0.8` or in the config file:  
+//!
+//! ```toml
+//! ratio_badges = ["tests_to_code", "doc_comments_per_code"]
+//! [ratio_thresholds]
+//! tests_to_code = { yellow = 0.3, green = 0.8 }
+//! ```
+//!
+//! ### Numbers
+//!
+//! The option `--number-format` writes the numbers in the badges, the markdown table and the trend charts:  
+//!
+//! 1. `plain`: `123456`, the default,  
+//! 2. `separated`: `123,456`. The separator can be changed with `--thousands-separator`, like `--thousands-separator .`,  
+//! 3. `compact`: `123k`, `12.3k`, `1.2M`. Below 100 units the number has 1 decimal, `--number-decimals 2` writes `1.23M`.  
+//!
+//! The JSON output, the baseline and the history log always have the raw numbers.  
+//!
+//! ## Configuration
+//!
+//! Every setting can come from the CLI flags, the `LMAKE_LOC_*` env vars, the config files and Cargo.toml.\
+//! The CLI flags win over the env vars, the env vars win over the config files and the config files win over Cargo.toml.\
+//! The include, exclude and generated patterns of all the sources are added together.  
+//! The config files are `.lmake_lines_of_code.toml` in the project root and in all the parent folders. The nearest file wins.\
+//! The file given with `--config` replaces them. In Cargo.toml the settings are in `[workspace.metadata.lmake_lines_of_code]`
+//! and `[package.metadata.lmake_lines_of_code]`, the package wins:  
+//!
+//! ```toml
+//! categories = ["code", "doc_comments", "tests"]
+//! exclude = ["src/generated/**"]
+//! file_filter = "respect-ignore"
+//! output_format = "markdown"
+//! remote = "upstream"
+//! readme = ["README.md", "docs/index.md"]
+//! badge_style = "flat-square"
+//! number_format = "compact"
+//! jobs = 4
+//! no_cache = false
+//! follow_symlinks = true
+//! ```
+//!
+//! The env vars have the same names in uppercase, like `LMAKE_LOC_BADGE_STYLE=flat-square`. Lists are separated by commas. An unknown `LMAKE_LOC_*` var prints a warning.\
+//! `LMAKE_LOC_PROJECT_ROOT` and `--project-root` choose the folder with Cargo.toml. `LMAKE_LOC_CONFIG` chooses the config file.  
+//! Every workspace member can override the workspace settings in its own Cargo.toml or `.lmake_lines_of_code.toml`.\
+//! The member adds its include, exclude and generated patterns. With `categories` the member counts only these categories,
+//! so `categories = ["code", "doc_comments", "comments", "tests"]` leaves its examples out of the examples badge.  
+//!
+//! ## Count at a git revision
+//!
+//! The option `--rev` counts the files at any git commit, tag or branch without checking it out:  
+//! `lmake_lines_of_code --rev v1.1.7`  
+//! The files are read from the git object database with `git ls-tree` and `git cat-file`.\
+//! Uncommitted files in the working tree are never counted. A revision that is not a commit stops with an error before counting.  
+//!
+//! ## History
+//!
+//! The subcommand `history` counts the lines at every tag, sorted by date:  
+//! `lmake_lines_of_code history`  
+//! or at every Nth commit on a branch:  
+//! `lmake_lines_of_code history --branch master --every 10`  
+//! It prints a markdown table and can write the time series as CSV or JSON for charts:  
+//! `lmake_lines_of_code history --csv loc_history.csv --json loc_history.json`  
+//! The counts are cached by commit in `target/lmake_lines_of_code/history_cache.json`, so reruns count only the new revisions.\
+//! Other include, exclude or generated patterns, other member settings or new classification rules drop the cache.  
+//!
+//! ## Diff between revisions
+//!
+//! The subcommand `diff` reports how every category changed between two revisions, for the release notes:  
+//! `lmake_lines_of_code diff v1.0.0..HEAD`  
+//! The added and removed lines are classified with the same rules as the count of lines:\
+//! the include and exclude patterns, the generated files and the member settings apply.\
+//! It prints the added, removed and net lines overall, per workspace member and per file, like `+12 -3 (+9)`,\
+//! and a sentence that can be pasted in the changelog:  
+//! `Lines of code v1.0.0..HEAD: src code grew by 120 lines, tests grew by 300 lines.`  
+//!
+//! ## Churn
+//!
+//! The subcommand `churn` shows the change activity: lines added and deleted per category, per week and per file:  
+//! `lmake_lines_of_code churn --since 2020-08-01`  
+//! The changed lines of every commit are classified like in the `diff` subcommand. Merge commits are skipped.\
+//! All the commits are read from one `git log -p` stream. The workspace members, their settings and the `include!` targets are read at HEAD.\
+//! The files are sorted by churn. Files with churn in the code, but without new tests, are marked with "no test growth".  
+//!
+//! ## Blame
+//!
+//! The subcommand `blame` shows which contributors wrote the code, doc comments, comments and tests currently in the repository:  
+//! `lmake_lines_of_code blame`  
+//! It runs `git blame --line-porcelain` for every counted file and attributes every line to its author.\
+//! The lines are classified like the count: the generated patterns, the `include!` targets and the member settings apply.\
+//! The identities are merged with the `.mailmap` file and by email.  
+//!
+//! ## Watch
+//!
+//! The subcommand `watch` counts the lines, prints the table and then counts again every time a file changes:  
+//! `lmake_lines_of_code watch --readme`  
+//! It polls the src, tests and examples folders of every workspace member and the Cargo.toml.\
+//! The poll reads only the modification times of the counted files and their folders. The tree is listed again only after a change.\
+//! With `--tracked` it also polls the git index, so `git add` is noticed.\
+//! If the files cannot be listed, for example Cargo.toml is invalid, the error is printed and watch keeps waiting for the next change.\
+//! A burst of saves is counted once, after the files are quiet for `--debounce` milliseconds (default 500).\
+//! With `--readme` the shield badges in README.md are updated on every change. Only the changed files are classified again, thanks to the cache.  
 //!
 //! ## Include into README.md
 //!
@@ -70,9 +241,97 @@
 //! 2. `[comment]: # (lmake_lines_of_code end)`  
 //!
 //! the CLI will include the shield badges code between them.  
+//! Use `--readme-target docs/index.md` to include the badges into other markdown files instead of README.md.  
 //! It will erase the previous content.  
+//! If the content does not change, the file is not written.  
 //! Use git diff to see the change.  
 //!
+//! ## Pre-commit hook
+//!
+//! The option `--staged` counts only the lines added and removed in the git index (`git diff --cached`) and prints the deltas for every category:  
+//! `lmake_lines_of_code --staged`  
+//! The subcommand `install-hook` writes the git pre-commit hook:  
+//! `lmake_lines_of_code install-hook`  
+//! Before every commit the hook prints the staged deltas, refreshes the shield badges in README.md and re-stages README.md when the counts change.  
+//! README.md is re-staged only if the badges were the only change in it. If README.md had unstaged edits, the hook stops the commit and you stage README.md yourself.  
+//! The badges count the files in the working tree, unstaged changes included.\
+//! An existing pre-commit hook is not overwritten without `--force`.  
+//!
+//! ## Check policy
+//!
+//! The subcommand `check-policy` is a gate for CI. It counts the lines, prints the violated rules and exits with an error:  
+//! `lmake_lines_of_code check-policy`  
+//! The rules are `[[policy]]` tables in the config file or `[[package.metadata.lmake_lines_of_code.policy]]` in Cargo.toml:  
+//!
+//! ```toml
+//! [[policy]]
+//! metric = "tests_to_code"
+//! min = 0.5
+//!
+//! [[policy]]
+//! metric = "doc_comments_per_code"
+//! per = 100
+//! min = 10
+//!
+//! [[policy]]
+//! metric = "src_code_lines"
+//! max = 800
+//! scope = "file"
+//! ```
+//!
+//! The metric is a field of the lines like `src_code_lines` or `tests_lines`, or a ratio like `tests_to_code`. With `per` the metric is multiplied, like per 100 code lines.\
+//! The scope is `workspace` (the default), `member` for every crate or `file` for every counted file. The list `members` limits the rule to some crates, so it needs the scope `member` or `file`.\
+//! The rules in the Cargo.toml or config file of a workspace member apply only to that member.  
+//!
+//! ## Baseline
+//!
+//! CI can fail when tests lines or doc comments drop compared to the main branch.\
+//! On the main branch save the lines of the workspace, every member and every file:  
+//! `lmake_lines_of_code --save-baseline loc-baseline.json`  
+//! On the pull request compare the lines with the saved baseline:  
+//! `lmake_lines_of_code --compare-baseline loc-baseline.json`  
+//! The regressions of the workspace, the members and the files are listed. The workspace and member regressions fail, the files are the details.\
+//! By default `tests_lines` and `src_doc_comment_lines` must not drop. The allowed drop in percent can be set for any metric:  
+//! `lmake_lines_of_code --compare-baseline loc-baseline.json --max-drop tests_lines=5 --max-drop src_doc_comment_lines=10`  
+//! or in the config file with `[baseline_max_drop]`. A metric that is not set keeps its default.  
+//!
+//! ## Trend
+//!
+//! The history log is opt-in. With `--history-log` or `history_log` in the config every run that counts the working tree
+//! (the default run, `--save-baseline`, `--compare-baseline` and `check-policy`) appends one JSON line
+//! with the UTC timestamp, the git commit and the lines:  
+//! `lmake_lines_of_code --history-log target/loc-history.jsonl`  
+//! The subcommand `trend` reads the log and prints an ASCII chart for every category.\
+//! With `--svg` it also writes an SVG chart with one panel per category, every panel with its own axis:  
+//! `lmake_lines_of_code --history-log target/loc-history.jsonl trend --svg loc-trend.svg`  
+//! Unlike the `history` subcommand, the trend does not walk the git history.  
+//!
+//! ## Library
+//!
+//! The crate can be used as a library. The functions `count_str` and `count_reader` count the lines of code that are not on disk,
+//! like files in a patch or in-memory sources:  
+//!
+//! ```rust
+//! use lmake_lines_of_code::*;
+//! let v = count_str("/// doc\nfn main() {}\n", FileKind::Src);
+//! assert_eq!(v.src_code_lines, 1);
+//! ```
+//!
+//! All the file operations for counting, the cache and README.md go through the `FileSystem` trait.\
+//! The field `AppObject.file_system` is the real file system by default. The `MemoryFileSystem` can hold archives, git trees or test fixtures,
+//! so the whole pipeline runs without touching the disk.  
+//! The CLI and the library share the same settings in `Config`. `Config::load` merges all the sources and `AppObject::builder()` accepts the config:  
+//!
+//! ```rust
+//! use lmake_lines_of_code::*;
+//! let config = Config {
+//!     exclude: vec!["src/generated/**".to_string()],
+//!     categories: vec!["code".to_string(), "tests".to_string()],
+//!     ..Config::default()
+//! };
+//! let app = AppObject::builder().config(config).build().unwrap();
+//! ```
+//!
 //! ## Install and run
 //!
 //! `cargo install lmake_lines_of_code`  
@@ -86,52 +345,151 @@
 
 // region: Clippy
 #![deny(unused_must_use)]
-
 // CONS: Unnecessary code.
 // PROS: more readable without knowing that the type is bool.
-#[allow(clippy::bool_comparison)]
+#![allow(clippy::bool_comparison)]
 // endregion: Clippy
 use mockall::predicate::*;
 use mockall::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use unwrap::unwrap;
 
+mod badge_link_mod;
+mod baseline_mod;
+mod blame_mod;
+mod cache_mod;
+mod churn_mod;
+mod config_mod;
 mod count_lines_mod;
+mod diff_mod;
+mod file_pattern_mod;
+mod file_system_mod;
+mod generated_mod;
+mod git_remote_mod;
+mod git_rev_mod;
+mod history_mod;
+mod ignore_mod;
+mod number_format_mod;
+mod policy_mod;
+mod ratio_mod;
 mod readme_include_mod;
+mod staged_mod;
+mod trend_mod;
 mod utilsmod;
+mod watch_mod;
 
+pub use badge_link_mod::*;
+pub use baseline_mod::*;
+pub use blame_mod::*;
+pub use cache_mod::*;
+pub use churn_mod::*;
+pub use config_mod::*;
 pub use count_lines_mod::*;
+pub use diff_mod::*;
+pub use file_pattern_mod::*;
+pub use file_system_mod::*;
+pub use generated_mod::*;
+pub use git_remote_mod::*;
+pub use git_rev_mod::*;
+pub use history_mod::*;
+pub use ignore_mod::*;
+pub use number_format_mod::*;
+pub use policy_mod::*;
+pub use ratio_mod::*;
 pub use readme_include_mod::*;
+pub use staged_mod::*;
+pub use trend_mod::*;
 pub use utilsmod::*;
+pub use watch_mod::*;
 
 /// An object to implement methods rather than functions.  
 /// The methods are always defined in Traits, to be testable/mockable.  
 /// Traits don't have access to fields, only to methods.  
-pub struct AppObject {}
+#[derive(Default)]
+pub struct AppObject {
+    /// the folder with Cargo.toml. Empty means the current directory.
+    /// The real file system and the git commands work in this folder.
+    pub project_root: PathBuf,
+    /// name of the git remote for the badge link. Empty means: upstream, origin, first remote.
+    pub remote_name: String,
+    /// links for every badge. Empty means the common link.
+    pub badge_links: BadgeLinks,
+    /// git revision (commit, tag, branch) to count. Empty means the working tree.
+    pub rev: String,
+    /// which files are counted: all, respect the ignore rules or only tracked by git.
+    pub file_filter: FileFilter,
+    /// include and exclude glob patterns relative to the project root.
+    pub file_patterns: FilePatterns,
+    /// number of threads for counting. 0 means one thread per CPU.
+    pub jobs: usize,
+    /// don't read or write the cache of counted files.
+    pub no_cache: bool,
+    /// skip the symlinks to files and folders. By default they are followed.
+    pub no_follow_symlinks: bool,
+    /// the badges in the output: code, doc_comments, comments, examples, tests. Empty means all.
+    pub categories: Vec<String>,
+    /// markdown table and badges or JSON.
+    pub output_format: OutputFormat,
+    /// the files where the badges are included between the markers. Empty means README.md.
+    pub readme_targets: Vec<String>,
+    /// the shields.io badge style, like flat-square. Empty means the default style.
+    pub badge_style: String,
+    /// plain, separated or compact numbers in the badges, the markdown table and the charts. JSON has the raw numbers.
+    pub number_format: NumberFormat,
+    /// the ratio badges and the thresholds for their colors. Empty means no ratio badges.
+    pub ratio_badges: RatioBadges,
+    /// the rules for the check-policy subcommand.
+    pub policies: Vec<PolicyRule>,
+    /// the allowed drop in percent by metric for --compare-baseline, on top of the default where tests lines and doc comments must not drop.
+    pub baseline_max_drop: BTreeMap<String, f64>,
+    /// the JSON lines file where every CLI run that counts appends its lines once. Empty means no history log.
+    pub history_log: String,
+    /// the settings of the workspace members that override the workspace settings.
+    pub member_overrides: BTreeMap<String, MemberOverride>,
+    /// the file system for counting, the cache and README.md. The default is the real file system.
+    pub file_system: Box<dyn FileSystem>,
+}
 
 impl AppObject {
     /// Constructor of the object that has all the public methods.  
-    /// No fields needed for now.  
     pub fn new() -> AppObject {
-        AppObject {}
+        AppObject::default()
     }
     /// Runs all the public methods. It is called by the CLI main fn.
     pub fn main(&self, link: &str) -> String {
         let text_to_include = self.text_to_include(link);
-
-        include_into_readme_md(&text_to_include);
+        self.include_into_readme_targets(&text_to_include);
         // return
         text_to_include
     }
+    /// Includes the text between the markers of README.md or of the readme targets.
+    pub fn include_into_readme_targets(&self, text_to_include: &str) {
+        if self.readme_targets.is_empty() {
+            include_into_readme_md_in(self.file_system.as_ref(), text_to_include);
+        }
+        for file_name in self.readme_targets.iter() {
+            include_into_file_in(self.file_system.as_ref(), file_name, text_to_include);
+        }
+    }
     pub fn text_to_include(&self, link: &str) -> String {
         let v = self.workspace_or_project_count_lines();
-        println!("{}", self.to_string_as_md_table(&v));
-
-        let link = if link.is_empty() {
-            self.process_git_remote()
+        self.text_to_include_for_lines(&v, link)
+    }
+    /// Prints the table of the lines and returns the shield badges for them.
+    pub fn text_to_include_for_lines(&self, v: &LinesOfCode, link: &str) -> String {
+        if self.output_format == OutputFormat::Json {
+            println!("{}", unwrap!(serde_json::to_string_pretty(v)));
         } else {
-            link.to_string()
-        };
-        let text_to_include = self.to_string_as_shield_badges(&v, &link);
-        println!("{}", &text_to_include);
+            println!("{}", self.to_string_as_md_table(v));
+        }
+
+        let link = self.resolve_link(link);
+        let links = self.badge_links.resolve(&link);
+        let text_to_include = self.to_string_as_shield_badges_with_links(v, &links);
+        if self.output_format == OutputFormat::Markdown {
+            println!("{}", &text_to_include);
+        }
         // return
         text_to_include
     }
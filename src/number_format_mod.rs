@@ -0,0 +1,169 @@
+// number_format_mod.rs
+//! Human-readable numbers in the badges, the markdown table and the charts.
+//!
+//! The numbers can be plain like `123456`, with thousands separators like `123,456`
+//! or compact like `123k` and `1.2M`. The JSON output always has the raw numbers.
+
+/// The units of the compact form.
+const COMPACT_UNITS: [(f64, &str); 3] = [(1e9, "G"), (1e6, "M"), (1e3, "k")];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// How the numbers are written.
+pub enum NumberStyle {
+    /// 123456
+    #[default]
+    Plain,
+    /// 123,456
+    Separated,
+    /// 123k, 1.2M
+    Compact,
+}
+
+impl std::str::FromStr for NumberStyle {
+    type Err = anyhow::Error;
+    /// Parses plain, separated or compact.
+    fn from_str(s: &str) -> anyhow::Result<NumberStyle> {
+        match s {
+            "plain" => Ok(NumberStyle::Plain),
+            "separated" => Ok(NumberStyle::Separated),
+            "compact" => Ok(NumberStyle::Compact),
+            _ => anyhow::bail!(
+                "Error: unknown number format {}. Use: plain, separated or compact.",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The style, the thousands separator and the decimals of the compact form.
+pub struct NumberFormat {
+    pub style: NumberStyle,
+    pub separator: String,
+    /// the decimals of the compact form below 100 units, like 1.2M or 12.3k
+    pub decimals: usize,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            style: NumberStyle::Plain,
+            separator: ",".to_string(),
+            decimals: 1,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Returns the number in this format.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let mut number_format = NumberFormat::default();
+    /// assert_eq!(number_format.format(123456), "123456");
+    /// number_format.style = NumberStyle::Separated;
+    /// assert_eq!(number_format.format(1234567), "1,234,567");
+    /// number_format.style = NumberStyle::Compact;
+    /// assert_eq!(number_format.format(123456), "123k");
+    /// assert_eq!(number_format.format(1234567), "1.2M");
+    /// assert_eq!(number_format.format(999), "999");
+    /// ```
+    pub fn format(&self, number: usize) -> String {
+        match self.style {
+            NumberStyle::Plain => number.to_string(),
+            NumberStyle::Separated => separate_thousands(number, &self.separator),
+            NumberStyle::Compact => self.compact(number),
+        }
+    }
+    fn compact(&self, number: usize) -> String {
+        let value = number as f64;
+        for (i, (unit_value, unit)) in COMPACT_UNITS.iter().enumerate() {
+            if value < *unit_value {
+                continue;
+            }
+            let scaled = value / unit_value;
+            // 3 significant digits are enough above 100 units
+            let decimals = if scaled >= 100.0 { 0 } else { self.decimals };
+            let rounded = format!("{:.*}", decimals, scaled);
+            // 999_950 rounds to 1000k, that is 1M
+            if rounded.parse::<f64>().unwrap_or(0.0) >= 1000.0 && i > 0 {
+                let (bigger_value, bigger_unit) = COMPACT_UNITS[i - 1];
+                return format!(
+                    "{}{}",
+                    trim_zeros(&format!("{:.*}", self.decimals, value / bigger_value)),
+                    bigger_unit
+                );
+            }
+            return format!("{}{}", trim_zeros(&rounded), unit);
+        }
+        // return
+        number.to_string()
+    }
+    /// Returns the number in this format for the shields.io badge path.
+    /// There `-` must be `--`, `_` must be `__` and a space is `_`.
+    /// Other characters of the separator that are not allowed in the url are percent-encoded.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let number_format = NumberFormat {
+    ///     style: NumberStyle::Separated,
+    ///     separator: "/".to_string(),
+    ///     decimals: 1,
+    /// };
+    /// assert_eq!(number_format.format_for_badge(1234), "1%2F234");
+    /// ```
+    pub fn format_for_badge(&self, number: usize) -> String {
+        let text = self
+            .format(number)
+            .replace('-', "--")
+            .replace('_', "__")
+            .replace(' ', "_");
+        // return
+        percent_encode(&text)
+    }
+}
+
+/// Encodes every byte as %XX, except the unreserved url characters and the comma.
+/// So `/`, `?`, `#` and `)` cannot break the url or the markdown link.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~,".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    // return
+    encoded
+}
+
+/// Removes the trailing zeros of the decimals: 1.0 -> 1, 1.50 -> 1.5.
+fn trim_zeros(number: &str) -> String {
+    if number.contains('.') {
+        number
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        number.to_string()
+    }
+}
+
+/// Writes the separator between every group of 3 digits.
+fn separate_thousands(number: usize, separator: &str) -> String {
+    let digits = number.to_string();
+    let mut text = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            text.push_str(separator);
+        }
+        text.push(digit);
+    }
+    // return
+    text
+}
@@ -0,0 +1,201 @@
+// policy_mod.rs
+//! Threshold policies for CI: the `check-policy` subcommand fails when a rule is violated.
+//!
+//! A rule has a metric, a min and/or max value and a scope:
+//! the whole workspace, every member or every file.
+//! The metric is a field of LinesOfCode like `src_code_lines` or a ratio like `tests_to_code`.
+//! The rules are written in the config files as `[[policy]]` tables.
+
+use crate::count_lines_mod::*;
+use crate::ratio_mod::Ratio;
+use crate::AppObject;
+
+use serde_derive::Deserialize;
+
+/// The fields of LinesOfCode that can be used as metric, in the order of LinesOfCode::as_array().
+pub const LINE_METRICS: [&str; 6] = [
+    "src_code_lines",
+    "src_doc_comment_lines",
+    "src_comment_lines",
+    "examples_lines",
+    "tests_lines",
+    "generated_lines",
+];
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+/// Where the rule is evaluated.
+pub enum PolicyScope {
+    /// the sum of all the members
+    #[default]
+    Workspace,
+    /// every member separately
+    Member,
+    /// every counted file separately
+    File,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// One rule, like `min tests_to_code = 0.5` or `max src_code_lines per file = 800`.
+pub struct PolicyRule {
+    pub metric: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// the metric is multiplied by per, so `doc_comments_per_code` with `per = 100` is per 100 code lines
+    pub per: Option<f64>,
+    #[serde(default)]
+    pub scope: PolicyScope,
+    /// the rule applies only to these members. Empty means all.
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// One rule that is not satisfied.
+pub struct Violation {
+    /// the description of the rule
+    pub rule: String,
+    /// workspace, the member name or the file path
+    pub target: String,
+    pub value: f64,
+}
+
+/// Returns the value of the metric: a field of LinesOfCode or a ratio.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let v = LinesOfCode::new(100, 20, 5, 50, 10);
+/// assert_eq!(metric_value(&v, "tests_lines").unwrap(), 50.0);
+/// assert_eq!(metric_value(&v, "tests_to_code").unwrap(), 0.5);
+/// assert!(metric_value(&v, "speed").is_err());
+/// ```
+pub fn metric_value(v: &LinesOfCode, metric: &str) -> anyhow::Result<f64> {
+    if let Some(pos) = LINE_METRICS.iter().position(|m| *m == metric) {
+        return Ok(v.as_array()[pos] as f64);
+    }
+    match metric.parse::<Ratio>() {
+        Ok(ratio) => Ok(v.ratio(ratio)),
+        Err(_) => anyhow::bail!(
+            "Error: unknown policy metric {}. Use: {} or a ratio.",
+            metric,
+            LINE_METRICS.join(", ")
+        ),
+    }
+}
+
+impl PolicyRule {
+    /// Returns an error for an unknown metric, a rule without min and max
+    /// or a workspace rule with members, because the workspace total ignores the members.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        metric_value(&LinesOfCode::default(), &self.metric)?;
+        anyhow::ensure!(
+            self.min.is_some() || self.max.is_some(),
+            "Error: policy {} has no min or max",
+            self.metric
+        );
+        anyhow::ensure!(
+            self.members.is_empty() || self.scope != PolicyScope::Workspace,
+            "Error: policy {} has members with the workspace scope. Use scope = \"member\" or \"file\".",
+            self.metric
+        );
+        Ok(())
+    }
+    /// The description like `min tests_to_code = 0.5 per member`.
+    pub fn describe(&self) -> String {
+        let per = match self.per {
+            Some(per) => format!(" per {}", per),
+            None => "".to_string(),
+        };
+        let scope = match self.scope {
+            PolicyScope::Workspace => "",
+            PolicyScope::Member => " per member",
+            PolicyScope::File => " per file",
+        };
+        let limits: Vec<String> = self
+            .min
+            .map(|min| format!("min {}{}{} = {}", self.metric, per, scope, min))
+            .into_iter()
+            .chain(
+                self.max
+                    .map(|max| format!("max {}{}{} = {}", self.metric, per, scope, max)),
+            )
+            .collect();
+        limits.join(", ")
+    }
+    /// Returns the violations of this rule in the report.
+    pub fn check(&self, report: &CountReport) -> anyhow::Result<Vec<Violation>> {
+        let applies =
+            |member: &str| self.members.is_empty() || self.members.iter().any(|m| m == member);
+        let targets: Vec<(String, &LinesOfCode)> = match self.scope {
+            PolicyScope::Workspace => vec![("workspace".to_string(), &report.total)],
+            PolicyScope::Member => report
+                .members
+                .iter()
+                .filter(|m| applies(&m.member))
+                .map(|m| (m.member.clone(), &m.lines_of_code))
+                .collect(),
+            PolicyScope::File => report
+                .files
+                .iter()
+                .filter(|f| applies(&f.member))
+                .map(|f| (f.path.clone(), &f.lines_of_code))
+                .collect(),
+        };
+        let mut violations = vec![];
+        for (target, v) in targets {
+            let value = metric_value(v, &self.metric)? * self.per.unwrap_or(1.0);
+            let too_low = self.min.is_some_and(|min| value < min);
+            let too_high = self.max.is_some_and(|max| value > max);
+            if too_low || too_high {
+                violations.push(Violation {
+                    rule: self.describe(),
+                    target,
+                    value,
+                });
+            }
+        }
+        // return
+        Ok(violations)
+    }
+}
+
+impl AppObject {
+    /// Counts the lines and returns the violations of all the policy rules.
+    pub fn check_policy(&self) -> anyhow::Result<Vec<Violation>> {
+        let report = self.count_report()?;
+        let mut violations = vec![];
+        for rule in self.policies.iter() {
+            violations.extend(rule.check(&report)?);
+        }
+        // return
+        Ok(violations)
+    }
+}
+
+/// Returns the markdown table of the violations.
+pub fn violations_to_md_table(violations: &[Violation]) -> String {
+    let mut table = String::from(
+        "
+| rule | crate or file | value |
+| :--- | :------------ | ----: |
+",
+    );
+    for violation in violations.iter() {
+        let target = if violation.target.is_empty() {
+            "project"
+        } else {
+            &violation.target
+        };
+        table.push_str(&format!(
+            "| {} | {} | {} |\n",
+            violation.rule,
+            target,
+            (violation.value * 100.0).round() / 100.0
+        ));
+    }
+    // return
+    table
+}
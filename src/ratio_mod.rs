@@ -0,0 +1,217 @@
+// ratio_mod.rs
+//! Ratios of the lines of code show the quality at a glance:
+//! doc comments per code line, comment density, tests to code and examples to code.
+//!
+//! The ratios are printed in the markdown table and can be shown as shield badges.
+//! The color of a ratio badge is red below the yellow threshold,
+//! yellow below the green threshold and green above.
+
+use crate::count_lines_mod::LinesOfCode;
+
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// One ratio computed from LinesOfCode.
+pub enum Ratio {
+    /// doc comment lines per code line
+    DocCommentsPerCode,
+    /// doc comments and comments in all the lines of src
+    CommentDensity,
+    /// tests lines per code line
+    TestsToCode,
+    /// examples lines per code line
+    ExamplesToCode,
+}
+
+impl Ratio {
+    /// All the ratios in the order of the output.
+    pub fn all() -> [Ratio; 4] {
+        [
+            Ratio::DocCommentsPerCode,
+            Ratio::CommentDensity,
+            Ratio::TestsToCode,
+            Ratio::ExamplesToCode,
+        ]
+    }
+    /// The name in the config and the CLI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ratio::DocCommentsPerCode => "doc_comments_per_code",
+            Ratio::CommentDensity => "comment_density",
+            Ratio::TestsToCode => "tests_to_code",
+            Ratio::ExamplesToCode => "examples_to_code",
+        }
+    }
+    /// The label in the table and the badge.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Ratio::DocCommentsPerCode => "doc comments per code",
+            Ratio::CommentDensity => "comment density",
+            Ratio::TestsToCode => "tests to code",
+            Ratio::ExamplesToCode => "examples to code",
+        }
+    }
+    /// The default thresholds for the badge colors.
+    pub fn default_thresholds(&self) -> RatioThresholds {
+        let (yellow, green) = match self {
+            Ratio::DocCommentsPerCode => (0.1, 0.25),
+            Ratio::CommentDensity => (0.1, 0.2),
+            Ratio::TestsToCode => (0.2, 0.5),
+            Ratio::ExamplesToCode => (0.05, 0.1),
+        };
+        RatioThresholds { yellow, green }
+    }
+}
+
+impl std::str::FromStr for Ratio {
+    type Err = anyhow::Error;
+    /// Parses the name of the ratio.
+    fn from_str(s: &str) -> anyhow::Result<Ratio> {
+        Ratio::all()
+            .iter()
+            .find(|ratio| ratio.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Ratio::all().iter().map(|r| r.name()).collect();
+                anyhow::anyhow!("Error: unknown ratio {}. Use: {}.", s, names.join(", "))
+            })
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+/// The ratio badge is red below yellow, yellow below green and green above.
+pub struct RatioThresholds {
+    pub yellow: f64,
+    pub green: f64,
+}
+
+impl RatioThresholds {
+    /// Parses `yellow:green`, like `0.2:0.5`.
+    pub fn parse(s: &str) -> anyhow::Result<RatioThresholds> {
+        let (yellow, green) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Error: thresholds {} are not yellow:green", s))?;
+        let parse = |v: &str| -> anyhow::Result<f64> {
+            v.trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Error: thresholds {}: {}", s, e))
+        };
+        // return
+        Ok(RatioThresholds {
+            yellow: parse(yellow)?,
+            green: parse(green)?,
+        })
+    }
+    /// The color of the badge for the value.
+    pub fn color(&self, value: f64) -> &'static str {
+        if value < self.yellow {
+            "red"
+        } else if value < self.green {
+            "yellow"
+        } else {
+            "green"
+        }
+    }
+}
+
+/// Parses `ratio_name=yellow:green` from the CLI and the env vars.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let (name, thresholds) = parse_ratio_thresholds("tests_to_code=0.3:0.6").unwrap();
+/// assert_eq!(name, "tests_to_code");
+/// assert_eq!(thresholds.color(0.4), "yellow");
+/// ```
+pub fn parse_ratio_thresholds(arg: &str) -> anyhow::Result<(String, RatioThresholds)> {
+    let (name, thresholds) = arg.split_once('=').ok_or_else(|| {
+        anyhow::anyhow!("Error: ratio threshold {} is not ratio=yellow:green", arg)
+    })?;
+    // return
+    Ok((name.trim().to_string(), RatioThresholds::parse(thresholds)?))
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// The ratio badges in the output and the thresholds for their colors.
+pub struct RatioBadges {
+    /// the ratios shown as badges. Empty means no ratio badges.
+    pub ratios: Vec<Ratio>,
+    /// the thresholds that are not the default
+    pub thresholds: BTreeMap<Ratio, RatioThresholds>,
+}
+
+impl RatioBadges {
+    /// The thresholds for the ratio, the default if not configured.
+    pub fn thresholds(&self, ratio: Ratio) -> RatioThresholds {
+        self.thresholds
+            .get(&ratio)
+            .copied()
+            .unwrap_or_else(|| ratio.default_thresholds())
+    }
+}
+
+impl LinesOfCode {
+    /// Doc comment lines per code line. 0 without code.
+    pub fn doc_comments_per_code(&self) -> f64 {
+        ratio_or_zero(self.src_doc_comment_lines, self.src_code_lines)
+    }
+    /// Doc comments and comments in all the lines of src. 0 without lines.
+    pub fn comment_density(&self) -> f64 {
+        let comments = self.src_doc_comment_lines + self.src_comment_lines;
+        ratio_or_zero(comments, comments + self.src_code_lines)
+    }
+    /// Tests lines per code line. 0 without code.
+    pub fn tests_to_code(&self) -> f64 {
+        ratio_or_zero(self.tests_lines, self.src_code_lines)
+    }
+    /// Examples lines per code line. 0 without code.
+    pub fn examples_to_code(&self) -> f64 {
+        ratio_or_zero(self.examples_lines, self.src_code_lines)
+    }
+    /// The value of one ratio.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    /// let v = LinesOfCode::new(100, 20, 5, 50, 10);
+    /// assert_eq!(v.ratio(Ratio::TestsToCode), 0.5);
+    /// assert_eq!(v.ratio(Ratio::DocCommentsPerCode), 0.2);
+    /// ```
+    pub fn ratio(&self, ratio: Ratio) -> f64 {
+        match ratio {
+            Ratio::DocCommentsPerCode => self.doc_comments_per_code(),
+            Ratio::CommentDensity => self.comment_density(),
+            Ratio::TestsToCode => self.tests_to_code(),
+            Ratio::ExamplesToCode => self.examples_to_code(),
+        }
+    }
+}
+
+fn ratio_or_zero(lines: usize, per_lines: usize) -> f64 {
+    if per_lines == 0 {
+        0.0
+    } else {
+        lines as f64 / per_lines as f64
+    }
+}
+
+/// Returns the markdown table with all the ratios, rounded to 2 decimals.
+pub fn ratios_to_md_table(v: &LinesOfCode) -> String {
+    let mut header = String::from("|");
+    let mut align = String::from("|");
+    let mut values = String::from("|");
+    for ratio in Ratio::all().iter() {
+        header.push_str(&format!(" {} |", ratio.label()));
+        align.push_str(&format!(" :{}: |", "-".repeat(ratio.label().len() - 2)));
+        values.push_str(&format!(
+            " {:^width$} |",
+            format!("{:.2}", v.ratio(*ratio)),
+            width = ratio.label().len()
+        ));
+    }
+    // return
+    format!("{}\n{}\n{}\n", header, align, values)
+}
@@ -1,9 +1,10 @@
 // include_into_readme_md_mod.rs
 //! Includes (writes, modifies) the shield badge code into README.md file.
 
+use crate::file_system_mod::*;
 #[allow(unused_imports)]
 use ansi_term::Colour::{Green, Red, Yellow};
-use std::fs;
+use std::path::Path;
 use unwrap::unwrap;
 
 /// Includes (writes, modifies) the shield badge code into README.md file.
@@ -17,25 +18,57 @@ use unwrap::unwrap;
 /// let v = include_into_readme_md(&text_to_include);
 /// ```
 pub fn include_into_readme_md(include_str: &str) {
+    include_into_readme_md_in(&RealFileSystem::default(), include_str);
+}
+
+/// Includes the shield badge code into README.md file in any file system.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// use std::path::Path;
+/// let file_system = MemoryFileSystem::new("/project").with_file(
+///     "README.md",
+///     "[comment]: # (lmake_lines_of_code start)\n[comment]: # (lmake_lines_of_code end)\n",
+/// );
+/// include_into_readme_md_in(&file_system, "badges");
+/// let readme = file_system.read_to_string(Path::new("README.md")).unwrap();
+/// assert!(readme.contains("start)\nbadges\n[comment]"));
+/// ```
+pub fn include_into_readme_md_in(file_system: &dyn FileSystem, include_str: &str) {
+    include_into_file_in(file_system, "README.md", include_str);
+}
+
+/// Includes the shield badge code between the markers of any markdown file, like docs/index.md.
+/// A file without the markers is not changed.
+pub fn include_into_file_in(file_system: &dyn FileSystem, file_name: &str, include_str: &str) {
     let start_delimiter = "[comment]: # (lmake_lines_of_code start)";
     let end_delimiter = "[comment]: # (lmake_lines_of_code end)";
-    let file_name = "README.md";
 
-    if let Ok(readme_content) = fs::read_to_string(file_name) {
+    if let Ok(readme_content) = file_system.read_to_string(Path::new(file_name)) {
         let mut new_readme_content = String::with_capacity(readme_content.len());
         if let Some(mut pos_start) = readme_content.find(start_delimiter) {
             pos_start += start_delimiter.len();
             if let Some(pos_end) = readme_content.find(end_delimiter) {
                 new_readme_content.push_str(&readme_content[..pos_start]);
-                new_readme_content.push_str("\n");
+                new_readme_content.push('\n');
                 new_readme_content.push_str(include_str);
-                new_readme_content.push_str("\n");
+                new_readme_content.push('\n');
                 new_readme_content.push_str(&readme_content[pos_end..]);
+                // don't touch the file if nothing changed, so git and the pre-commit hook see no change
+                if new_readme_content == readme_content {
+                    println!(
+                        "include_into_readme_md unchanged file: {}",
+                        Green.paint(file_name)
+                    );
+                    return;
+                }
                 println!(
                     "include_into_readme_md write file: {}",
                     Green.paint(file_name)
                 );
-                unwrap!(fs::write(file_name, new_readme_content));
+                unwrap!(file_system.write(Path::new(file_name), new_readme_content.as_bytes()));
             }
         }
     }
@@ -0,0 +1,117 @@
+// staged_mod.rs
+//! Counts only the staged changes and installs the pre-commit hook.
+//!
+//! The staged changes are the lines added and removed in the git index: `git diff --cached`.
+//! The pre-commit hook refreshes the README badges and re-stages README.md when only the badges changed.
+
+use crate::diff_mod::*;
+use crate::git_rev_mod::*;
+use crate::AppObject;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Yellow};
+use std::path::{Path, PathBuf};
+
+/// The marker in the hook file, to recognize the hook written by this app.
+pub const HOOK_MARKER: &str = "# lmake_lines_of_code pre-commit hook";
+
+/// The pre-commit hook script.
+/// The README.md is re-staged only if the badges were the only change in it.
+/// If README.md had unstaged edits before, the hook stops and the user stages it.
+/// The badges count the working tree, not the index, like the CLI without `--staged`.
+pub const PRE_COMMIT_HOOK: &str = r#"#!/bin/sh
+# lmake_lines_of_code pre-commit hook
+# refreshes the shield badges in README.md and re-stages it when only the badges changed
+# the badges count the files in the working tree, unstaged changes included
+readme_dirty=0
+if [ -f README.md ] && ! git diff --quiet -- README.md; then
+    readme_dirty=1
+fi
+readme_before=""
+if [ -f README.md ]; then
+    readme_before=$(git hash-object README.md)
+fi
+lmake_lines_of_code --staged || exit 1
+lmake_lines_of_code || exit 1
+if [ -f README.md ] && [ "$(git hash-object README.md)" != "$readme_before" ]; then
+    if [ $readme_dirty = 1 ]; then
+        echo "lmake_lines_of_code: README.md has unstaged edits and new badges. Stage it and commit again."
+        exit 1
+    fi
+    git add README.md
+fi
+"#;
+
+impl AppObject {
+    /// Returns the delta of lines staged in the git index, compared to HEAD.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmake_lines_of_code::*;
+    ///
+    /// let app = AppObject::new();
+    /// let delta = app.staged_delta().unwrap();
+    /// println!("{}", delta_to_md_tables(&delta));
+    /// ```
+    pub fn staged_delta(&self) -> anyhow::Result<RevisionsDelta> {
+        // before the first commit there is no HEAD
+        let head_entries = git_ls_tree(&self.project_root, "HEAD").unwrap_or_default();
+        let index_entries = git_ls_files_stage(&self.project_root)?;
+        let mut args = GIT_DIFF_U0_ARGS.to_vec();
+        args.push("--cached");
+        let output = git_output(&self.project_root, &args)?;
+        self.delta_from_git_diff("HEAD", "index", &head_entries, &index_entries, &output)
+    }
+    /// Writes the pre-commit hook into the git hooks folder.
+    /// An existing hook that was not written by this app is overwritten only with force.
+    pub fn install_pre_commit_hook(&self, force: bool) -> anyhow::Result<PathBuf> {
+        // the path is relative to the project root
+        let hook_path = self.project_root.join(
+            git_output(
+                &self.project_root,
+                &["rev-parse", "--git-path", "hooks/pre-commit"],
+            )?
+            .trim(),
+        );
+        if let Ok(existing) = self.file_system.read_to_string(&hook_path) {
+            anyhow::ensure!(
+                force || existing.contains(HOOK_MARKER),
+                "Error: the hook {} already exists. Use --force to overwrite it.",
+                hook_path.display()
+            );
+        }
+        // write() creates the hooks folder
+        self.file_system
+            .write(&hook_path, PRE_COMMIT_HOOK.as_bytes())?;
+        self.file_system.set_executable(&hook_path)?;
+        println!(
+            "install-hook write file: {}",
+            Green.paint(hook_path.to_string_lossy())
+        );
+        // return
+        Ok(hook_path)
+    }
+}
+
+/// Lists the files in the git index, like git_ls_tree() lists a revision.
+/// The paths are relative to the project root. Only the stage 0 (not in conflict) is used.
+pub fn git_ls_files_stage(project_root: &Path) -> anyhow::Result<Vec<GitTreeEntry>> {
+    let output = git_output(project_root, &["ls-files", "--stage", "-z"])?;
+    let mut entries = vec![];
+    // every entry is: mode SP oid SP stage TAB path NUL
+    for entry in output.split('\0').filter(|e| !e.is_empty()) {
+        let (meta, path) = entry
+            .split_once('\t')
+            .ok_or_else(|| anyhow::anyhow!("Error: git ls-files entry {}", entry))?;
+        let mut meta = meta.split(' ');
+        if let (Some(_mode), Some(oid), Some("0")) = (meta.next(), meta.next(), meta.next()) {
+            entries.push(GitTreeEntry {
+                oid: oid.to_string(),
+                path: path.to_string(),
+            });
+        }
+    }
+    // return
+    Ok(entries)
+}
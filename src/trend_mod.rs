@@ -0,0 +1,274 @@
+// trend_mod.rs
+//! Local LOC history log and trend charts.
+//!
+//! With the opt-in `history_log` setting every run appends one JSON line
+//! with the timestamp, the git commit and the LinesOfCode.
+//! The `trend` subcommand reads the log and renders an ASCII chart in the terminal
+//! and an SVG chart with one panel per category, without walking the git history.
+
+use crate::churn_mod::civil_from_days;
+use crate::count_lines_mod::*;
+use crate::diff_mod::CATEGORY_NAMES;
+use crate::git_rev_mod::git_head_commit;
+use crate::number_format_mod::NumberFormat;
+use crate::AppObject;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Yellow};
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The rows of the ASCII chart for one category.
+pub const ASCII_CHART_HEIGHT: usize = 8;
+/// The maximum columns of the ASCII chart. Longer logs are sampled.
+pub const ASCII_CHART_WIDTH: usize = 60;
+
+/// The colors of the categories in the SVG chart, like the badges.
+const SVG_COLORS: [&str; 6] = ["green", "blue", "purple", "gold", "orange", "gray"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// One line of the history log.
+pub struct HistoryLogEntry {
+    /// UTC time in ISO 8601 format, like 2020-08-22T10:00:00Z
+    pub timestamp: String,
+    /// the git commit of HEAD. Empty without git.
+    pub commit: String,
+    pub lines_of_code: LinesOfCode,
+}
+
+impl AppObject {
+    /// Appends the lines to the history log file, if the history log is enabled.
+    pub fn append_history_log(&self, v: &LinesOfCode) -> anyhow::Result<()> {
+        if self.history_log.is_empty() {
+            return Ok(());
+        }
+        let entry = HistoryLogEntry {
+            timestamp: utc_timestamp(SystemTime::now()),
+            commit: git_head_commit(self.file_system.as_ref(), &self.file_system.current_dir()?)
+                .unwrap_or_default(),
+            lines_of_code: v.clone(),
+        };
+        let path = Path::new(&self.history_log);
+        let mut content = self.file_system.read_to_string(path).unwrap_or_default();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&serde_json::to_string(&entry)?);
+        content.push('\n');
+        self.file_system.write(path, content.as_bytes())?;
+        println!("append history log: {}", Green.paint(&self.history_log));
+        Ok(())
+    }
+    /// Reads all the entries of the history log file.
+    pub fn read_history_log(&self, path: &str) -> anyhow::Result<Vec<HistoryLogEntry>> {
+        let content = self
+            .file_system
+            .read_to_string(Path::new(path))
+            .map_err(|e| anyhow::anyhow!("Error: history log {}: {}", path, e))?;
+        parse_history_log(&content)
+            .map_err(|e| anyhow::anyhow!("Error: history log {}: {}", path, e))
+    }
+}
+
+/// Parses the JSON lines of the history log. Empty lines are skipped.
+pub fn parse_history_log(content: &str) -> anyhow::Result<Vec<HistoryLogEntry>> {
+    let mut entries = vec![];
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: HistoryLogEntry =
+            serde_json::from_str(line).map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// The UTC time in ISO 8601 format.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_598_090_400);
+/// assert_eq!(utc_timestamp(time), "2020-08-22T10:00:00Z");
+/// ```
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let secs_of_day = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Returns the entries to draw: all of them, or evenly sampled with the first and last entry.
+fn sample<T>(items: &[T], max: usize) -> Vec<&T> {
+    if items.len() <= max || max < 2 {
+        return items.iter().collect();
+    }
+    (0..max)
+        .map(|i| &items[i * (items.len() - 1) / (max - 1)])
+        .collect()
+}
+
+/// Returns an ASCII chart for every category that has lines.
+/// The first column is the oldest entry. Longer logs are sampled to ASCII_CHART_WIDTH columns.
+pub fn trend_to_ascii_charts(entries: &[HistoryLogEntry], number_format: &NumberFormat) -> String {
+    let entries = sample(entries, ASCII_CHART_WIDTH);
+    let mut text = String::new();
+    let (first, last) = match (entries.first(), entries.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return text,
+    };
+    for (i, name) in CATEGORY_NAMES.iter().enumerate() {
+        let values: Vec<usize> = entries
+            .iter()
+            .map(|e| e.lines_of_code.as_array()[i])
+            .collect();
+        let max = values.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            continue;
+        }
+        let min = values.iter().copied().min().unwrap_or(0);
+        text.push_str(&format!(
+            "\n{}: {} -> {}\n",
+            name,
+            number_format.format(values[0]),
+            number_format.format(values[values.len() - 1])
+        ));
+        let label_width = number_format.format(max).len();
+        for row in (0..ASCII_CHART_HEIGHT).rev() {
+            let label = if row == ASCII_CHART_HEIGHT - 1 {
+                number_format.format(max)
+            } else if row == 0 {
+                number_format.format(min)
+            } else {
+                "".to_string()
+            };
+            text.push_str(&format!("{:>width$} |", label, width = label_width));
+            for value in values.iter() {
+                let level = if max == min {
+                    0
+                } else {
+                    (value - min) * (ASCII_CHART_HEIGHT - 1) / (max - min)
+                };
+                text.push(if level == row { '*' } else { ' ' });
+            }
+            text.push('\n');
+        }
+        text.push_str(&format!(
+            "{:>width$} +{}\n",
+            "",
+            "-".repeat(values.len()),
+            width = label_width
+        ));
+    }
+    text.push_str(&format!(
+        "\n{} to {}, {} entries\n",
+        first.timestamp,
+        last.timestamp,
+        entries.len()
+    ));
+    // return
+    text
+}
+
+/// Returns the SVG chart with one panel for every category that has lines.
+/// Every panel has its own y axis from 0 to the maximum of that category,
+/// so the small categories are readable below the big ones.
+/// The x axis is the order of the entries.
+pub fn trend_to_svg(entries: &[HistoryLogEntry], number_format: &NumberFormat) -> String {
+    let (width, panel_height, margin) = (800.0, 160.0, 50.0);
+    let right = width - margin;
+    let categories: Vec<usize> = (0..CATEGORY_NAMES.len())
+        .filter(|i| entries.iter().any(|e| e.lines_of_code.as_array()[*i] > 0))
+        .collect();
+    // the dates are below the last panel
+    let height = 20.0 + categories.len() as f64 * panel_height + 30.0;
+    let x = |i: usize| {
+        if entries.len() < 2 {
+            margin
+        } else {
+            margin + i as f64 * (right - margin) / (entries.len() - 1) as f64
+        }
+    };
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">
+<rect width="{w}" height="{h}" fill="white"/>
+"#,
+        w = width,
+        h = height
+    );
+    for (panel, i) in categories.iter().enumerate() {
+        let top = 20.0 + panel as f64 * panel_height;
+        let (plot_top, plot_bottom) = (top + 20.0, top + panel_height - 20.0);
+        let max = entries
+            .iter()
+            .map(|e| e.lines_of_code.as_array()[*i])
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let y = |value: usize| plot_bottom - value as f64 * (plot_bottom - plot_top) / max as f64;
+        svg.push_str(&format!(
+            r#"<text x="{m}" y="{title}" fill="{color}">{name}</text>
+<line x1="{m}" y1="{b}" x2="{r}" y2="{b}" stroke="black"/>
+<line x1="{m}" y1="{t}" x2="{m}" y2="{b}" stroke="black"/>
+<text x="{m}" y="{max_y}" text-anchor="end">{max} </text>
+<text x="{m}" y="{b}" text-anchor="end">0 </text>
+"#,
+            m = margin,
+            r = right,
+            t = plot_top,
+            b = plot_bottom,
+            title = top + 12.0,
+            max_y = plot_top + 4.0,
+            color = SVG_COLORS[*i],
+            name = CATEGORY_NAMES[*i],
+            max = xml_escape(&number_format.format(max))
+        ));
+        let points: Vec<String> = entries
+            .iter()
+            .enumerate()
+            .map(|(n, e)| format!("{:.1},{:.1}", x(n), y(e.lines_of_code.as_array()[*i])))
+            .collect();
+        svg.push_str(&format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>\n",
+            SVG_COLORS[*i],
+            points.join(" ")
+        ));
+    }
+    if let (Some(first), Some(last)) = (entries.first(), entries.last()) {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\">{}</text>\n<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n",
+            margin,
+            height - 10.0,
+            xml_escape(first.timestamp.get(..10).unwrap_or(&first.timestamp)),
+            right,
+            height - 10.0,
+            xml_escape(last.timestamp.get(..10).unwrap_or(&last.timestamp))
+        ));
+    }
+    svg.push_str("</svg>\n");
+    // return
+    svg
+}
+
+/// Escapes the text for the SVG: the separator and the timestamps come from the user.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
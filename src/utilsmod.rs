@@ -1,11 +1,20 @@
 // utilsmod.rs
 //! Often used common utility fn.
+use crate::file_system_mod::*;
 use unwrap::unwrap;
 
-use std::{fs, io, path::Path};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 /// Traverse dir and its sub-dir, but avoid excluded dirs.
 /// The find_file and the exclude dir strings must start with /.
+/// Symlinks are followed only with follow_symlinks, else they are skipped.
+/// Every directory is visited only once (by device and inode),
+/// so a symlink to a parent directory does not cause an infinite recursion.
+/// The paths are PathBuf, because file names can be non-UTF-8.
 ///
 /// ## Example
 ///
@@ -21,42 +30,151 @@ use std::{fs, io, path::Path};
 ///         "/.git".to_string(),
 ///         "/target".to_string(),
 ///         "/docs".to_string()
-///     ]
+///     ],
+///     // follow the symlinks
+///     true,
 /// ).unwrap();
 /// for rs_file_name in files.iter() {
-///     println!("{}", &rs_file_name);
+///     println!("{}", rs_file_name.display());
 /// }
 /// ```
 pub fn traverse_dir_with_exclude_dir(
     dir: &Path,
     find_file: &str,
     exclude_dirs: &[String],
-) -> io::Result<Vec<String>> {
-    // if the parameter is /*.rs, I can eliminate /*
-    let find_file = &find_file.replace("/*", "");
+    follow_symlinks: bool,
+) -> io::Result<Vec<PathBuf>> {
+    traverse_dir_in(
+        &RealFileSystem::default(),
+        dir,
+        find_file,
+        exclude_dirs,
+        follow_symlinks,
+    )
+}
 
+/// Traverse dir like traverse_dir_with_exclude_dir(), but in any file system.
+pub fn traverse_dir_in(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    find_file: &str,
+    exclude_dirs: &[String],
+    follow_symlinks: bool,
+) -> io::Result<Vec<PathBuf>> {
+    // if the parameter is /*.rs, I can eliminate /*
+    let find_file = find_file.replace("/*", "");
+    let mut visited = HashSet::new();
     let mut v = Vec::new();
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let str_path = unwrap!(path.to_str());
-            if path.is_dir() {
-                let mut is_excluded = false;
-                for excl in exclude_dirs {
-                    if str_path.ends_with(excl) {
-                        is_excluded = true;
-                        break;
-                    }
-                }
-                if !is_excluded {
-                    let mut sub_v = traverse_dir_with_exclude_dir(&path, find_file, exclude_dirs)?;
-                    v.append(&mut sub_v);
-                }
-            } else if str_path.ends_with(find_file) {
-                v.push(str_path.to_string());
+    if file_system.is_dir(dir) {
+        traverse_dir_recursive(
+            file_system,
+            dir,
+            &find_file,
+            exclude_dirs,
+            follow_symlinks,
+            &mut visited,
+            &mut v,
+        )?;
+    }
+    Ok(v)
+}
+
+fn traverse_dir_recursive(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    find_file: &str,
+    exclude_dirs: &[String],
+    follow_symlinks: bool,
+    visited: &mut HashSet<(u64, u64)>,
+    v: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    if !visited.insert(file_system.metadata(dir)?.id) {
+        // already visited through another symlink
+        return Ok(());
+    }
+    for entry in file_system.read_dir(dir)? {
+        let path = entry.path;
+        if entry.is_symlink && !follow_symlinks {
+            continue;
+        }
+        // metadata() follows the symlink. A broken symlink is skipped.
+        let is_dir = if entry.is_symlink {
+            match file_system.metadata(&path) {
+                Ok(metadata) => metadata.is_dir,
+                Err(_) => continue,
             }
+        } else {
+            entry.is_dir
+        };
+        let str_path = path.to_string_lossy();
+        if is_dir {
+            let is_excluded = exclude_dirs.iter().any(|excl| str_path.ends_with(excl));
+            if !is_excluded {
+                traverse_dir_recursive(
+                    file_system,
+                    &path,
+                    find_file,
+                    exclude_dirs,
+                    follow_symlinks,
+                    visited,
+                    v,
+                )?;
+            }
+        } else if str_path.ends_with(find_file) {
+            v.push(path);
         }
     }
-    Ok(v)
+    Ok(())
+}
+
+/// The number of threads: the jobs argument or the number of CPUs for 0.
+pub fn jobs_or_cpus(jobs: usize) -> usize {
+    if jobs > 0 {
+        jobs
+    } else {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+}
+
+/// Maps every item with a bounded pool of threads.
+/// The results are in the order of the items, regardless of thread scheduling.
+/// With jobs 0 the pool has one thread per CPU.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let squares = parallel_map(&[1, 2, 3, 4], 2, |x| x * x);
+/// assert_eq!(squares, vec![1, 4, 9, 16]);
+/// ```
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs_or_cpus(jobs).min(items.len());
+    if jobs <= 1 {
+        return items.iter().map(f).collect();
+    }
+    // every thread takes the next free index, the result is stored at that index
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    let results_mutex = Mutex::new(&mut results);
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() {
+                    break;
+                }
+                let r = f(&items[i]);
+                unwrap!(results_mutex.lock())[i] = Some(r);
+            });
+        }
+    });
+    // return
+    results.into_iter().map(|r| unwrap!(r)).collect()
 }
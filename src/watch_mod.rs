@@ -0,0 +1,194 @@
+// watch_mod.rs
+//! Watch mode: re-counts the lines when the files change.
+//!
+//! The counted files and the src, tests and examples folders of every workspace member are polled.
+//! The poll reads only the metadata. The tree is listed again only after a change.
+//! A change is handled only after the files are quiet for the debounce time,
+//! so a burst of saves is counted once.
+//! Only the changed files are classified again, thanks to the cache of counted files.
+
+use crate::count_lines_mod::*;
+use crate::git_remote_mod::*;
+use crate::ignore_mod::*;
+use crate::AppObject;
+
+#[allow(unused_imports)]
+use ansi_term::Colour::{Green, Red, Yellow};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// How often the files are polled for changes.
+pub const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The size and mtime (nanoseconds) of every watched file. The key is the file path.
+pub type WatchSnapshot = BTreeMap<String, (u64, u64)>;
+
+impl AppObject {
+    /// Returns the paths to watch: the counted files, all the folders of src, tests and examples,
+    /// Cargo.toml and with the tracked filter the git index.
+    /// A new or deleted file changes the modification time of its folder,
+    /// so the tree is listed again only after a change.
+    pub fn watch_paths(&self) -> anyhow::Result<Vec<String>> {
+        let current_dir = self.file_system.current_dir()?;
+        let mut paths: Vec<String> = self
+            .workspace_or_project_files()?
+            .into_iter()
+            .map(|f| f.path.to_string_lossy().into_owned())
+            .collect();
+        let members = self.workspace_members()?;
+        let members = if members.is_empty() {
+            vec!["".to_string()]
+        } else {
+            members
+        };
+        let mut visited = HashSet::new();
+        for member in members.iter() {
+            for file_kind in FileKind::all().iter() {
+                let dir = current_dir.join(member).join(file_kind.folder_name());
+                if self.file_system.is_dir(&dir) {
+                    self.watch_dirs(&dir, &mut visited, &mut paths)?;
+                }
+            }
+        }
+        // the workspace members can change
+        paths.push("Cargo.toml".to_string());
+        // git add changes the tracked files without changing the working tree
+        if self.file_filter == FileFilter::Tracked {
+            let git_dir = find_git_dir(self.file_system.as_ref(), &current_dir)?;
+            paths.push(git_dir.join("index").to_string_lossy().into_owned());
+        }
+        // return
+        Ok(paths)
+    }
+    /// Adds the folder and all its subfolders, like the traversal of the counted files.
+    fn watch_dirs(
+        &self,
+        dir: &Path,
+        visited: &mut HashSet<(u64, u64)>,
+        paths: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        if !visited.insert(self.file_system.metadata(dir)?.id) {
+            // already visited through another symlink
+            return Ok(());
+        }
+        paths.push(dir.to_string_lossy().into_owned());
+        for entry in self.file_system.read_dir(dir)? {
+            if entry.is_symlink && self.no_follow_symlinks {
+                continue;
+            }
+            let str_path = entry.path.to_string_lossy();
+            let excluded = exclude_dirs()
+                .iter()
+                .any(|exclude| str_path.ends_with(exclude));
+            if !excluded && self.file_system.is_dir(&entry.path) {
+                self.watch_dirs(&entry.path, visited, paths)?;
+            }
+        }
+        // return
+        Ok(())
+    }
+    /// Returns the size and mtime of every watched path.
+    /// A missing path is not in the snapshot.
+    pub fn watch_snapshot_of(&self, paths: &[String]) -> WatchSnapshot {
+        paths
+            .iter()
+            .filter_map(|path| {
+                let metadata = self.file_system.metadata(Path::new(path)).ok()?;
+                Some((path.clone(), (metadata.len, metadata.modified)))
+            })
+            .collect()
+    }
+    /// Returns the size and mtime of every counted file, of their folders and of Cargo.toml.
+    /// A new, deleted or modified file changes the snapshot.
+    pub fn watch_snapshot(&self) -> anyhow::Result<WatchSnapshot> {
+        // return
+        Ok(self.watch_snapshot_of(&self.watch_paths()?))
+    }
+    /// Counts the lines and prints the table. Then waits for changes and counts again forever.
+    /// With update_readme the shield badges in README.md are updated on every change.
+    /// The poll reads only the metadata of the watched paths. The tree is listed again after a change.
+    /// If the files cannot be listed while watching, the error is printed and the previous snapshot is kept.
+    /// If a file cannot be counted, the error is printed and the watch goes on.
+    pub fn watch(&self, link: &str, update_readme: bool, debounce: Duration) -> anyhow::Result<()> {
+        let mut paths = self.watch_paths()?;
+        let mut snapshot = self.watch_snapshot_of(&paths);
+        self.watch_count(link, update_readme);
+        loop {
+            thread::sleep(WATCH_POLL_INTERVAL);
+            let mut quiet_snapshot = self.watch_snapshot_of(&paths);
+            if quiet_snapshot == snapshot {
+                continue;
+            }
+            // debounce: wait until the files are quiet
+            loop {
+                thread::sleep(debounce);
+                let next_snapshot = self.watch_snapshot_of(&paths);
+                if next_snapshot == quiet_snapshot {
+                    break;
+                }
+                quiet_snapshot = next_snapshot;
+            }
+            // a new file is found only by listing the tree again
+            let new_snapshot = match self.watch_paths() {
+                Ok(new_paths) => {
+                    paths = new_paths;
+                    self.watch_snapshot_of(&paths)
+                }
+                Err(e) => {
+                    println!("{}", Red.paint(e.to_string()));
+                    // the same change is not reported again
+                    snapshot = quiet_snapshot;
+                    continue;
+                }
+            };
+            for path in changed_paths(&snapshot, &new_snapshot).iter() {
+                println!("changed: {}", Yellow.paint(path));
+            }
+            snapshot = new_snapshot;
+            self.watch_count(link, update_readme);
+        }
+    }
+    /// A file that cannot be counted prints the error and the watch continues.
+    fn watch_count(&self, link: &str, update_readme: bool) {
+        match self.count_report() {
+            Ok(report) => {
+                if update_readme {
+                    let text_to_include = self.text_to_include_for_lines(&report.total, link);
+                    self.include_into_readme_targets(&text_to_include);
+                } else {
+                    println!("{}", self.to_string_as_md_table(&report.total));
+                }
+            }
+            Err(e) => println!("{}", Red.paint(e.to_string())),
+        }
+        println!("{}", Green.paint("watching for changes, ctrl+c to stop"));
+    }
+}
+
+/// Returns the paths that are new, deleted or modified in the new snapshot.
+///
+/// ## Example
+///
+/// ```
+/// use lmake_lines_of_code::*;
+/// let mut old = WatchSnapshot::new();
+/// old.insert("src/lib.rs".to_string(), (10, 1));
+/// old.insert("src/a.rs".to_string(), (10, 1));
+/// let mut new = old.clone();
+/// new.insert("src/lib.rs".to_string(), (11, 2));
+/// new.remove("src/a.rs");
+/// assert_eq!(changed_paths(&old, &new), vec!["src/a.rs", "src/lib.rs"]);
+/// ```
+pub fn changed_paths(old: &WatchSnapshot, new: &WatchSnapshot) -> Vec<String> {
+    let mut paths: Vec<String> = new
+        .iter()
+        .filter(|(path, v)| old.get(*path) != Some(v))
+        .map(|(path, _)| path.clone())
+        .chain(old.keys().filter(|path| !new.contains_key(*path)).cloned())
+        .collect();
+    paths.sort();
+    // return
+    paths
+}
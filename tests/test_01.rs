@@ -8,13 +8,90 @@ use lmake_lines_of_code::*;
 const REPO_LINK: &str = "https://github.com/LucianoBestia/lmake_lines_of_code/";
 // the count of lines of this project will change with time. Keep it actual for testing.
 const LINES_OF_CODE: LinesOfCode = LinesOfCode {
-    src_code_lines: 379,
-    src_doc_comment_lines: 269,
-    src_comment_lines: 56,
-    tests_lines: 142,
+    src_code_lines: 5113,
+    src_doc_comment_lines: 1840,
+    src_comment_lines: 202,
+    tests_lines: 1390,
     examples_lines: 0,
+    generated_lines: 0,
 };
 
+/// The single package project in memory in /project with the files.
+fn memory_project(files: &[(&str, &str)]) -> MemoryFileSystem {
+    files.iter().fold(
+        MemoryFileSystem::new("/project").with_file("Cargo.toml", "[package]\nname = \"x\"\n"),
+        |file_system, (path, content)| file_system.with_file(path, content),
+    )
+}
+
+/// The app that works only in memory, without the cache.
+fn memory_app(file_system: MemoryFileSystem) -> AppObject {
+    let mut app = AppObject::new();
+    app.file_system = Box::new(file_system);
+    app.no_cache = true;
+    // return
+    app
+}
+
+/// The app built from the config for the project in memory, without the cache.
+fn memory_app_with_config(config: Config, file_system: MemoryFileSystem) -> AppObject {
+    let mut app = AppObject::builder()
+        .config(config)
+        .file_system(Box::new(file_system))
+        .build()
+        .unwrap();
+    app.no_cache = true;
+    // return
+    app
+}
+
+/// An empty git repository in the temp folder, with a local user for the commits.
+fn git_repo(name: &str) -> std::path::PathBuf {
+    let repo = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&repo);
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["config", "user.name", "Test"]);
+    git(&repo, &["config", "user.email", "test@example.com"]);
+    git(&repo, &["config", "commit.gpgsign", "false"]);
+    // return
+    repo
+}
+
+/// Runs git in the repository and returns the stdout.
+fn git(repo: &std::path::Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}", args);
+    // return
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Writes the files and commits all the changes.
+fn git_commit(repo: &std::path::Path, files: &[(&str, &str)], message: &str) {
+    for (path, content) in files.iter() {
+        let path = repo.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    git(repo, &["add", "-A"]);
+    git(repo, &["commit", "-q", "-m", message]);
+}
+
+/// The app for the git repository, without the cache.
+fn git_app(repo: &std::path::Path) -> AppObject {
+    let mut app = AppObject::new();
+    app.project_root = repo.to_path_buf();
+    app.file_system = Box::new(RealFileSystem::new(repo));
+    app.no_cache = true;
+    // return
+    app
+}
+
 #[test]
 /// automatically finds GitHub git remote repository
 /// 3 accepted possibilities: error, git ssh, git https
@@ -140,3 +217,1174 @@ fn test_07_include_in_readme() {
     included.push_str(&readme_content[pos_start..pos_end]);
     assert_eq!(included, text_to_include);
 }
+
+#[test]
+/// parses the remotes from .git/config and chooses one
+/// with the fallback order: upstream, origin, first remote
+fn test_08_git_config_remotes_choose() {
+    let config = r#"[core]
+	bare = false
+[remote "origin"]
+	url = git@github.com:fork_user/lmake_lines_of_code.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[remote "upstream"]
+	url = https://github.com/LucianoBestia/lmake_lines_of_code.git
+[branch "master"]
+	remote = origin
+"#;
+    let remotes = parse_git_config_remotes(config);
+    assert_eq!(remotes.len(), 2);
+    assert_eq!(
+        choose_remote(&remotes, "").unwrap().url,
+        "https://github.com/LucianoBestia/lmake_lines_of_code.git"
+    );
+    assert_eq!(choose_remote(&remotes, "origin").unwrap().name, "origin");
+    assert!(choose_remote(&remotes, "missing").is_none());
+    // without upstream and origin the first remote is used
+    let remotes = parse_git_remote_v(
+        "gitlab  git@gitlab.com:group/sub/repo.git (fetch)\ngitlab  git@gitlab.com:group/sub/repo.git (push)\n",
+    );
+    let remote = choose_remote(&remotes, "").unwrap();
+    assert_eq!(
+        remote_url_to_link(&remote.url).unwrap(),
+        "https://gitlab.com/group/sub/repo/"
+    );
+}
+
+#[test]
+/// remote urls in all the forms: ssh, scp-like, https with and without .git
+fn test_09_remote_url_to_link() {
+    for url in &[
+        "git@github.com:LucianoBestia/lmake_lines_of_code.git",
+        "https://github.com/LucianoBestia/lmake_lines_of_code",
+        "https://github.com/LucianoBestia/lmake_lines_of_code.git/",
+        "ssh://git@github.com:22/LucianoBestia/lmake_lines_of_code.git",
+        "git://github.com/LucianoBestia/lmake_lines_of_code.git",
+        "github.com:LucianoBestia/lmake_lines_of_code.git",
+    ] {
+        assert_eq!(
+            remote_url_to_link(url).unwrap(),
+            "https://github.com/LucianoBestia/lmake_lines_of_code/"
+        );
+    }
+    // local remotes have no link
+    for url in &[
+        "/home/user/local_repo",
+        "file:///srv/git/user/repo.git",
+        "../user/repo.git",
+        "user/repo",
+        "C:/repos/user/repo.git",
+    ] {
+        assert!(remote_url_to_link(url).is_err(), "{}", url);
+    }
+}
+
+#[test]
+/// a worktree has a .git file with gitdir: and the config is in the commondir
+fn test_10_git_config_remotes_worktree() {
+    use std::fs;
+    let tmp = std::env::temp_dir().join("lmake_lines_of_code_test_10");
+    let _ = fs::remove_dir_all(&tmp);
+    let main_git = tmp.join("main/.git");
+    fs::create_dir_all(main_git.join("worktrees/wt")).unwrap();
+    fs::write(
+        main_git.join("config"),
+        "[remote \"origin\"]\n\turl = git@github.com:user/repo.git\n",
+    )
+    .unwrap();
+    fs::write(main_git.join("worktrees/wt/commondir"), "../..\n").unwrap();
+    fs::create_dir_all(tmp.join("wt/src")).unwrap();
+    fs::write(tmp.join("wt/.git"), "gitdir: ../main/.git/worktrees/wt\n").unwrap();
+
+    let remotes = git_config_remotes(&RealFileSystem::default(), &tmp.join("wt/src")).unwrap();
+    assert_eq!(remotes[0].url, "git@github.com:user/repo.git");
+
+    // without remotes in .git/config, like with an included config file, git remote -v is used
+    let repo = tmp.join("included");
+    fs::create_dir_all(&repo).unwrap();
+    std::process::Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&repo)
+        .status()
+        .unwrap();
+    fs::write(
+        tmp.join("remotes.config"),
+        "[remote \"origin\"]\n\turl = https://github.com/user/included.git\n",
+    )
+    .unwrap();
+    let mut config = fs::read_to_string(repo.join(".git/config")).unwrap();
+    config.push_str("[include]\n\tpath = ../../remotes.config\n");
+    fs::write(repo.join(".git/config"), config).unwrap();
+    let mut app = AppObject::new();
+    app.project_root = repo.clone();
+    app.file_system = Box::new(RealFileSystem::new(&repo));
+    let remotes = app.git_remotes().unwrap();
+    assert_eq!(remotes[0].url, "https://github.com/user/included.git");
+    let _ = fs::remove_dir_all(&tmp);
+}
+
+#[test]
+/// the link fallback from Cargo.toml: package.repository, package.homepage, workspace.package.repository
+fn test_11_link_from_cargo_toml() {
+    let link = link_from_cargo_toml(
+        r#"[package]
+name = "x"
+repository = "https://github.com/user/x"
+homepage = "https://x.example.com"
+"#,
+    );
+    assert_eq!(link.unwrap(), "https://github.com/user/x/");
+    let link = link_from_cargo_toml(
+        r#"[package]
+name = "x"
+repository = { workspace = true }
+
+[workspace]
+members = ["x"]
+
+[workspace.package]
+repository = "https://gitlab.com/user/workspace/"
+"#,
+    );
+    assert_eq!(link.unwrap(), "https://gitlab.com/user/workspace/");
+    assert!(link_from_cargo_toml("[package]\nname = \"x\"\n").is_none());
+}
+
+#[test]
+/// every badge can link to a different url
+fn test_12_badge_links() {
+    let app = AppObject::new();
+    let v = LinesOfCode::new(11, 22, 33, 44, 55);
+    let mut links = BadgeLinks::default();
+    links.set_from_arg("tests={link}tree/master/tests").unwrap();
+    links.set_from_arg("examples=http://examples").unwrap();
+    assert!(links.set_from_arg("unknown=http://x").is_err());
+    let badges = app.to_string_as_shield_badges_with_links(&v, &links.resolve("http://website/"));
+    assert_eq!(badges,"[![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-11-green.svg)](http://website/)\n[![Lines in Doc comments](https://img.shields.io/badge/Lines_in_Doc_comments-22-blue.svg)](http://website/)\n[![Lines in Comments](https://img.shields.io/badge/Lines_in_comments-33-purple.svg)](http://website/)\n[![Lines in examples](https://img.shields.io/badge/Lines_in_examples-55-yellow.svg)](http://examples)\n[![Lines in tests](https://img.shields.io/badge/Lines_in_tests-44-orange.svg)](http://website/tree/master/tests)\n");
+}
+
+#[test]
+/// counts the files from the git object database, not from the working tree
+fn test_13_count_lines_at_rev() {
+    let entries = git_ls_tree(std::path::Path::new(""), "HEAD").unwrap();
+    assert!(entries.iter().any(|e| e.path == "src/lib.rs"));
+    let app = AppObject::new();
+    let v = app.workspace_or_project_count_lines_at_rev("HEAD").unwrap();
+    assert!(v.src_code_lines > 0);
+    assert!(v.tests_lines > 0);
+    assert!(app
+        .workspace_or_project_count_lines_at_rev("no_such_revision")
+        .is_err());
+    assert_eq!(app.verify_rev("HEAD").unwrap().len(), 40);
+    assert!(app.verify_rev("no_such_revision").is_err());
+    // a missing object is an error, the git process is killed
+    let missing = vec!["0".repeat(40), entries[0].oid.clone()];
+    assert!(git_cat_file_batch(std::path::Path::new(""), &missing).is_err());
+}
+
+#[test]
+/// LOC history over the commits of the current branch, cached by commit hash
+fn test_14_history_commits() {
+    let app = AppObject::new();
+    let source = HistorySource::Commits {
+        branch: "HEAD".to_string(),
+        every: 1,
+    };
+    let history = app.history(&source).unwrap();
+    assert!(!history.is_empty());
+    assert!(std::path::Path::new(HISTORY_CACHE_FILE).exists());
+    // the second run reads from the cache and returns the same
+    assert_eq!(app.history(&source).unwrap(), history);
+    // other file patterns do not use the cached counts
+    let mut app = AppObject::new();
+    app.file_patterns.add_exclude("tests/**").unwrap();
+    let source = HistorySource::Commits {
+        branch: "HEAD".to_string(),
+        every: 1000,
+    };
+    let history = app.history(&source).unwrap();
+    assert_eq!(history.last().unwrap().lines_of_code.tests_lines, 0);
+}
+
+#[test]
+/// the history as CSV and markdown table
+fn test_15_history_output() {
+    let history = vec![HistoryPoint {
+        rev_name: "v1.0.0".to_string(),
+        commit: "0123456789abcdef".to_string(),
+        date: "2020-08-22T10:00:00+02:00".to_string(),
+        lines_of_code: LinesOfCode::new(11, 22, 33, 44, 55),
+    }];
+    assert_eq!(history_to_csv(&history), "revision,commit,date,src_code_lines,src_doc_comment_lines,src_comment_lines,examples_lines,tests_lines,generated_lines\nv1.0.0,0123456789abcdef,2020-08-22T10:00:00+02:00,11,22,33,55,44,0\n");
+    assert!(
+        history_to_md_table(&history).contains("| v1.0.0 | 2020-08-22 | 11 | 22 | 33 | 55 | 44 |")
+    );
+    assert!(history_to_json(&history)
+        .unwrap()
+        .contains("\"tests_lines\": 44"));
+}
+
+#[test]
+/// the changed lines from git diff -U0 are counted in the category of that line
+fn test_16_diff_classify_changed_lines() {
+    let output = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2 +2,2 @@ fn x()
+--- removed line that looks like a header
++/// doc comment
++let x = 1;
+@@ -10,0 +12 @@
++    assert!(true);
+";
+    let file_diffs = parse_diff_u0(output);
+    assert_eq!(file_diffs.len(), 1);
+    assert_eq!(file_diffs[0].old_path.as_deref(), Some("src/lib.rs"));
+    assert_eq!(file_diffs[0].removed_lines, vec![2]);
+    assert_eq!(file_diffs[0].added_lines, vec![2, 3, 12]);
+
+    let new_content = "fn x() {}\n/// doc comment\nlet x = 1;\n#[cfg(test)]\nmod tests {\n\n\n\n\n\n\n    assert!(true);\n";
+    let categories = classify_lines(new_content.as_bytes(), FileKind::Src);
+    let mut added = LinesOfCode::default();
+    count_changed_lines(&mut added, &categories, &file_diffs[0].added_lines);
+    assert_eq!(added, LinesOfCode::new(1, 1, 0, 1, 0));
+    assert_eq!(
+        split_rev_range("v1.0.0"),
+        ("v1.0.0".to_string(), "HEAD".to_string())
+    );
+}
+
+#[test]
+/// the delta between the same revisions is empty
+fn test_17_diff_revisions() {
+    let app = AppObject::new();
+    let delta = app.diff_revisions("HEAD..HEAD").unwrap();
+    assert_eq!(delta.total, LinesOfCodeDelta::default());
+    assert!(delta_to_changelog_text(&delta).ends_with("no change."));
+
+    // two commits in a new repository, so the test does not depend on the history of this project
+    let repo = git_repo("lmake_lines_of_code_test_17");
+    git_commit(
+        &repo,
+        &[
+            ("Cargo.toml", "[package]\nname = \"x\"\n"),
+            ("src/lib.rs", "fn a() {}\n"),
+            ("tests/t.rs", "fn t() {}\n"),
+        ],
+        "first",
+    );
+    git_commit(
+        &repo,
+        &[
+            ("src/lib.rs", "/// doc\nfn a() {}\nfn b() {}\n"),
+            ("src/remote.rs", "/// doc\nfn r() {}\n// comment\n"),
+            ("tests/t.rs", "fn t() {}\nfn u() {}\n"),
+        ],
+        "second",
+    );
+    let range = "HEAD~1..HEAD";
+    let app = git_app(&repo);
+    let delta = app.diff_revisions(range).unwrap();
+    let file_delta = &delta.per_file["src/remote.rs"];
+    assert_eq!(file_delta.removed, LinesOfCode::default());
+    assert_eq!(file_delta.added, LinesOfCode::new(1, 1, 1, 0, 0));
+    assert_eq!(delta.total.added, LinesOfCode::new(2, 2, 1, 1, 0));
+    // the file rows have the added, removed and net lines of every category
+    assert!(delta_to_md_tables(&delta)
+        .contains("| src/lib.rs | +1 -0 (+1) | +1 -0 (+1) | +0 -0 (+0) | +0 -0 (+0) | +0 -0 (+0) | +0 -0 (+0) |"));
+    // the excluded files are not in the diff and the generated files count only generated lines
+    let mut app = git_app(&repo);
+    app.file_patterns.add_exclude("tests/**").unwrap();
+    app.file_patterns.add_generated("src/remote.rs").unwrap();
+    let delta = app.diff_revisions(range).unwrap();
+    assert!(!delta.per_file.contains_key("tests/t.rs"));
+    assert_eq!(delta.total.added.tests_lines, 0);
+    let file_delta = &delta.per_file["src/remote.rs"];
+    assert_eq!(file_delta.added.src_code_lines, 0);
+    assert_eq!(file_delta.added.generated_lines, 3);
+    // the git config of the user does not change the diff
+    git(&repo, &["config", "diff.mnemonicPrefix", "true"]);
+    git(&repo, &["config", "diff.noprefix", "true"]);
+    git(&repo, &["config", "diff.external", "echo"]);
+    let app = git_app(&repo);
+    let delta = app.diff_revisions(range).unwrap();
+    assert_eq!(delta.total.added, LinesOfCode::new(2, 2, 1, 1, 0));
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+/// mailmap merges identities: all 4 forms of .mailmap lines
+fn test_18_blame_mailmap() {
+    let mailmap = Mailmap::parse(
+        "# comment
+Luciano Bestia <luciano.bestia@gmail.com>
+<luciano.bestia@gmail.com> <luciano@old-laptop>
+Proper Name <proper@example.com> <commit@example.com>
+Other Name <other@example.com> Wrong Name <shared@example.com>
+",
+    );
+    assert_eq!(
+        mailmap.map("luciano", "luciano.bestia@gmail.com"),
+        (
+            "Luciano Bestia".to_string(),
+            "luciano.bestia@gmail.com".to_string()
+        )
+    );
+    assert_eq!(
+        mailmap.map("lb", "luciano@old-laptop").1,
+        "luciano.bestia@gmail.com"
+    );
+    assert_eq!(mailmap.map("x", "commit@example.com").0, "Proper Name");
+    assert_eq!(
+        mailmap.map("Wrong Name", "shared@example.com").0,
+        "Other Name"
+    );
+    assert_eq!(
+        mailmap.map("Right Name", "shared@example.com").0,
+        "Right Name"
+    );
+
+    let line_authors = parse_blame_line_porcelain(
+        "0123456789012345678901234567890123456789 1 1 1
+author Luciano
+author-mail <luciano.bestia@gmail.com>
+filename src/lib.rs
+\t/// doc comment
+0123456789012345678901234567890123456789 2 2
+author Luciano
+author-mail <luciano.bestia@gmail.com>
+filename src/lib.rs
+\tfn x() {}
+",
+    );
+    assert_eq!(line_authors.len(), 2);
+    assert_eq!(line_authors[1].1, "luciano.bestia@gmail.com");
+
+    // blame classifies the lines like count: generated patterns and include! targets
+    let repo = git_repo("lmake_lines_of_code_test_18");
+    git_commit(
+        &repo,
+        &[
+            ("Cargo.toml", "[package]\nname = \"x\"\n"),
+            ("src/lib.rs", "/// doc\ninclude!(\"inc.rs\");\n"),
+            ("src/inc.rs", "fn i() {}\n"),
+            ("src/gen.rs", "fn g() {}\nfn h() {}\n"),
+        ],
+        "first",
+    );
+    let mut app = git_app(&repo);
+    app.file_patterns.add_generated("src/gen.rs").unwrap();
+    let authors = app.blame_authors().unwrap();
+    assert_eq!(authors.len(), 1);
+    assert_eq!(authors[0].email, "test@example.com");
+    assert_eq!(
+        authors[0].lines_of_code,
+        LinesOfCode {
+            src_code_lines: 1,
+            src_doc_comment_lines: 1,
+            generated_lines: 3,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        authors[0].lines_of_code,
+        app.workspace_or_project_count_lines()
+    );
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+/// the week of the churn starts on Monday
+fn test_19_churn_week_start() {
+    assert_eq!(week_start("2020-08-22T10:00:00+02:00"), "2020-08-17");
+    assert_eq!(week_start("2020-08-17"), "2020-08-17");
+    assert_eq!(week_start("2021-01-01T00:00:00Z"), "2020-12-28");
+    assert_eq!(week_start("2020-03-01"), "2020-02-24");
+}
+
+#[test]
+/// in a linear history the net churn of all commits is the count at HEAD
+fn test_20_churn_since() {
+    let app = AppObject::new();
+    let churn = app.churn("2000-01-01").unwrap();
+    let head = app.workspace_or_project_count_lines_at_rev("HEAD").unwrap();
+    let head_array: Vec<i64> = head.as_array().iter().map(|x| *x as i64).collect();
+    assert_eq!(churn.total.net().to_vec(), head_array);
+    assert!(churn_to_md_tables(&churn).contains("src/lib.rs"));
+
+    // the root commit is counted also with log.showRoot = false
+    let repo = git_repo("lmake_lines_of_code_test_20");
+    git(&repo, &["config", "log.showRoot", "false"]);
+    git(&repo, &["config", "diff.mnemonicPrefix", "true"]);
+    git_commit(
+        &repo,
+        &[
+            ("Cargo.toml", "[package]\nname = \"x\"\n"),
+            ("src/lib.rs", "/// doc\nfn a() {}\n"),
+        ],
+        "first",
+    );
+    git_commit(
+        &repo,
+        &[
+            ("src/lib.rs", "fn a() {}\nfn b() {}\n"),
+            ("tests/t.rs", "fn t() {}\n"),
+        ],
+        "second",
+    );
+    let churn = git_app(&repo).churn("2000-01-01").unwrap();
+    assert_eq!(churn.total.added, LinesOfCode::new(2, 1, 0, 1, 0));
+    assert_eq!(churn.total.removed, LinesOfCode::new(0, 1, 0, 0, 0));
+    assert_eq!(churn.per_file["src/lib.rs"].added.src_code_lines, 2);
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+/// the staged changes are compared to HEAD, the index is read with git ls-files
+fn test_21_staged() {
+    let entries = git_ls_files_stage(std::path::Path::new("")).unwrap();
+    assert!(entries.iter().any(|e| e.path == "src/lib.rs"));
+    let app = AppObject::new();
+    let delta = app.staged_delta().unwrap();
+    assert_eq!(delta.to_rev, "index");
+    // the hook written by this app is recognized by the marker
+    assert!(PRE_COMMIT_HOOK.contains(HOOK_MARKER));
+    assert!(PRE_COMMIT_HOOK.contains("git add README.md"));
+    // README.md with unstaged edits is not re-staged
+    assert!(PRE_COMMIT_HOOK.contains("readme_dirty"));
+    // the staged lines are found with the mnemonic prefixes i/ and w/ of the user config
+    let repo = git_repo("lmake_lines_of_code_test_21_staged");
+    git_commit(
+        &repo,
+        &[
+            ("Cargo.toml", "[package]\nname = \"x\"\n"),
+            ("src/lib.rs", "fn a() {}\n"),
+        ],
+        "first",
+    );
+    std::fs::write(repo.join("src/lib.rs"), "fn a() {}\n/// doc\nfn b() {}\n").unwrap();
+    git(&repo, &["add", "src/lib.rs"]);
+    git(&repo, &["config", "diff.mnemonicPrefix", "true"]);
+    let delta = git_app(&repo).staged_delta().unwrap();
+    assert_eq!(delta.total.added, LinesOfCode::new(1, 1, 0, 0, 0));
+    let _ = std::fs::remove_dir_all(&repo);
+
+    // the hook is installed, a foreign hook is overwritten only with force
+    let repo = git_repo("lmake_lines_of_code_test_21_hook");
+    let readme =
+        "# x\n[comment]: # (lmake_lines_of_code start)\n[comment]: # (lmake_lines_of_code end)\n";
+    git_commit(
+        &repo,
+        &[
+            ("Cargo.toml", "[package]\nname = \"x\"\n"),
+            ("src/lib.rs", "fn a() {}\n"),
+            ("README.md", readme),
+            (".gitignore", "/target\n"),
+        ],
+        "first",
+    );
+    let app = git_app(&repo);
+    let hook_path = app.install_pre_commit_hook(false).unwrap();
+    // the hook written by this app is recognized by the marker
+    app.install_pre_commit_hook(false).unwrap();
+    std::fs::write(&hook_path, "#!/bin/sh\nexit 0\n").unwrap();
+    assert!(app.install_pre_commit_hook(false).is_err());
+    app.install_pre_commit_hook(true).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&hook_path).unwrap(),
+        PRE_COMMIT_HOOK
+    );
+
+    if cfg!(unix) {
+        // the hook runs the binary of this crate
+        let bin_dir = std::path::Path::new(env!("CARGO_BIN_EXE_lmake_lines_of_code"))
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap());
+        let commit = |message: &str| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(["commit", "-q", "-m", message])
+                .env("PATH", &path)
+                .output()
+                .unwrap()
+                .status
+                .success()
+        };
+        // README.md without other changes gets the new badges in the same commit
+        std::fs::write(repo.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        git(&repo, &["add", "src/lib.rs"]);
+        assert!(commit("second"));
+        assert!(git(&repo, &["show", "HEAD:README.md"]).contains("Lines_in_Rust-2-"));
+        assert!(git(&repo, &["status", "--porcelain", "README.md"]).is_empty());
+        // README.md with unstaged edits stops the commit and stays unstaged
+        std::fs::write(repo.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        git(&repo, &["add", "src/lib.rs"]);
+        let edited = std::fs::read_to_string(repo.join("README.md")).unwrap() + "edit\n";
+        std::fs::write(repo.join("README.md"), edited).unwrap();
+        assert!(!commit("third"));
+        assert_eq!(
+            git(&repo, &["diff", "--cached", "--name-only"]),
+            "src/lib.rs\n"
+        );
+    }
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+/// the ignore rules: deeper .gitignore wins, .ignore has priority, negation whitelists
+fn test_22_ignore_rules() {
+    let dir = std::path::Path::new("/project");
+    let memory_file_system = MemoryFileSystem::new("/project")
+        .with_file(".git/info/exclude", "*.tmp.rs\n")
+        .with_file(".gitignore", "/src/generated/\n*.bak.rs\n")
+        .with_file("src/.ignore", "!keep.bak.rs\n");
+    let rules = IgnoreRules::new(&memory_file_system, dir);
+    assert!(!rules.is_ignored(&dir.join("src/lib.rs"), false));
+    assert!(rules.is_ignored(&dir.join("src/generated/out.rs"), false));
+    assert!(rules.is_ignored(&dir.join("src/old.bak.rs"), false));
+    assert!(!rules.is_ignored(&dir.join("src/keep.bak.rs"), false));
+    assert!(rules.is_ignored(&dir.join("src/a.tmp.rs"), false));
+
+    // the files of this project are all tracked by git
+    let file_system = RealFileSystem::default();
+    let current_dir = std::env::current_dir().unwrap();
+    let files = vec![current_dir.join("src/lib.rs")];
+    let tracked = filter_files(
+        &file_system,
+        files.clone(),
+        &current_dir,
+        FileFilter::Tracked,
+    )
+    .unwrap();
+    assert_eq!(tracked, files);
+    // without git the tracked filter is an error, not an empty list
+    let tracked = filter_files(
+        &file_system,
+        files,
+        std::path::Path::new("/nonexistent_folder"),
+        FileFilter::Tracked,
+    );
+    assert!(tracked.is_err());
+}
+
+#[test]
+/// include and exclude glob patterns relative to the project root
+fn test_23_file_patterns() {
+    let mut file_patterns = FilePatterns::default();
+    file_patterns.add_exclude("src/generated/**").unwrap();
+    file_patterns.add_exclude("**/*_pb.rs").unwrap();
+    assert!(file_patterns.is_counted("src/lib.rs"));
+    assert!(!file_patterns.is_counted("src/generated/a.rs"));
+    assert!(!file_patterns.is_counted("src/proto/msg_pb.rs"));
+    // * does not match the folder separator
+    file_patterns.add_include("src/*.rs").unwrap();
+    assert!(file_patterns.is_counted("src/lib.rs"));
+    assert!(!file_patterns.is_counted("src/bin/main.rs"));
+    assert!(!file_patterns.is_counted("tests/test_01.rs"));
+    assert!(file_patterns.add_include("src/[").is_err());
+
+    let mut app = AppObject::new();
+    app.file_patterns.add_exclude("tests/**").unwrap();
+    let files = app.workspace_or_project_files().unwrap();
+    assert!(files.iter().all(|f| f.file_kind != FileKind::Tests));
+    let v = app.workspace_or_project_count_lines_at_rev("HEAD").unwrap();
+    assert_eq!(v.tests_lines, 0);
+    let current_dir = std::env::current_dir().unwrap();
+    assert!(project_files_to_md_table(&files, &current_dir).contains("| src/lib.rs | src |"));
+}
+
+#[test]
+/// generated files: header marker, path pattern and include! target
+fn test_24_generated_lines() {
+    let content = "// This file is @generated by prost-build.\npub struct A {}\n/// doc\n";
+    let categories = classify_lines(content.as_bytes(), FileKind::Src);
+    assert_eq!(categories, vec![LineCategory::Generated; 3]);
+    // the marker in a doc comment does not make the file generated
+    let categories = classify_lines("/// @generated\nfn a() {}\n".as_bytes(), FileKind::Src);
+    assert_eq!(categories[1], LineCategory::SrcCode);
+    // a plain "do not edit" and markers after the first item are not generated headers
+    let categories = classify_lines(
+        "// do not edit without review\nfn a() {}\n// @generated\n".as_bytes(),
+        FileKind::Src,
+    );
+    assert_eq!(categories[1], LineCategory::SrcCode);
+    let content = "/* automatically generated by rust-bindgen */\npub struct A {}\n";
+    let categories = classify_lines(content.as_bytes(), FileKind::Src);
+    assert_eq!(categories[1], LineCategory::Generated);
+    let content = "/*\n * Copyright\n * @generated by a tool\n */\npub struct A {}\n";
+    let categories = classify_lines(content.as_bytes(), FileKind::Src);
+    assert_eq!(categories[4], LineCategory::Generated);
+
+    let targets = include_targets(
+        "src/lib.rs",
+        r#"include!("./bindings.rs"); include_str!("x.txt"); include!(concat!(env!("OUT_DIR"), "/a.rs"));
+// include!("commented.rs");
+/* include!("block.rs");
+include!("block.rs"); */"#,
+    );
+    assert_eq!(targets, vec!["src/bindings.rs".to_string()]);
+
+    let mut file_patterns = FilePatterns::default();
+    file_patterns.add_generated("src/proto/**").unwrap();
+    assert!(file_patterns.is_generated("src/proto/msg.rs"));
+    assert!(!file_patterns.is_generated("src/lib.rs"));
+
+    let app = AppObject::new();
+    let v = app.workspace_or_project_count_lines();
+    assert_eq!(v.generated_lines, LINES_OF_CODE.generated_lines);
+    assert!(app.to_string_as_md_table(&v).contains("| generated |"));
+}
+
+#[test]
+/// the count with many threads is the same as with one thread
+fn test_25_parallel_jobs() {
+    let items: Vec<usize> = (0..100).collect();
+    assert_eq!(
+        parallel_map(&items, 8, |x| x * 2),
+        parallel_map(&items, 1, |x| x * 2)
+    );
+    assert!(parallel_map(&Vec::<usize>::new(), 4, |x| *x).is_empty());
+    assert!(jobs_or_cpus(0) >= 1);
+
+    let mut app = AppObject::new();
+    app.jobs = 1;
+    let one_thread = app.workspace_or_project_count_lines();
+    app.jobs = 8;
+    assert_eq!(app.workspace_or_project_count_lines(), one_thread);
+    assert_eq!(
+        app.workspace_or_project_count_lines_at_rev("HEAD").unwrap(),
+        AppObject::new()
+            .workspace_or_project_count_lines_at_rev("HEAD")
+            .unwrap()
+    );
+}
+
+#[test]
+/// the count with the cache is the same as without the cache
+fn test_26_file_cache() {
+    let mut app = AppObject::new();
+    app.no_cache = true;
+    let without_cache = app.workspace_or_project_count_lines();
+    app.no_cache = false;
+    assert_eq!(app.workspace_or_project_count_lines(), without_cache);
+    assert!(std::path::Path::new(FILE_CACHE_FILE).exists());
+    // the second run reads from the cache
+    assert_eq!(app.workspace_or_project_count_lines(), without_cache);
+    assert_eq!(fnv1a_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+}
+
+#[test]
+/// the watch snapshot changes when a counted file changes
+fn test_27_watch_snapshot() {
+    let app = AppObject::new();
+    let snapshot = app.watch_snapshot().unwrap();
+    assert!(snapshot.contains_key("Cargo.toml"));
+    assert!(snapshot.keys().any(|path| path.ends_with("src/lib.rs")));
+    assert!(changed_paths(&snapshot, &app.watch_snapshot().unwrap()).is_empty());
+    let mut modified = snapshot.clone();
+    modified.insert("Cargo.toml".to_string(), (0, 0));
+    assert_eq!(changed_paths(&snapshot, &modified), vec!["Cargo.toml"]);
+
+    // the folders are watched, so a new file is found without listing the tree on every poll
+    let app = memory_app(memory_project(&[
+        ("src/lib.rs", "fn a() {}\n"),
+        ("src/a/b/c.rs", "fn c() {}\n"),
+    ]));
+    let paths = app.watch_paths().unwrap();
+    assert!(paths.contains(&"/project/src".to_string()));
+    assert!(paths.contains(&"/project/src/a".to_string()));
+    assert!(paths.contains(&"/project/src/a/b/c.rs".to_string()));
+    // a missing Cargo.toml is an error, not a panic
+    let app = memory_app(MemoryFileSystem::new("/project").with_file("src/lib.rs", ""));
+    assert!(app.workspace_members().is_err());
+    assert!(app.watch_snapshot().is_err());
+    // a file that cannot be read while counting is an error, not a panic
+    let mut app = memory_app(memory_project(&[("src/lib.rs", "fn a() {}\n")]));
+    app.file_system = Box::new(UnreadableFileSystem {
+        inner: memory_project(&[("src/lib.rs", "fn a() {}\n"), ("src/gone.rs", "")]),
+        unreadable: std::path::PathBuf::from("/project/src/gone.rs"),
+    });
+    let err = app.count_report().err().unwrap().to_string();
+    assert!(err.starts_with("Error: /project/src/gone.rs"));
+}
+
+/// The file system in memory where one file is listed, but cannot be read.
+struct UnreadableFileSystem {
+    inner: MemoryFileSystem,
+    unreadable: std::path::PathBuf,
+}
+
+impl FileSystem for UnreadableFileSystem {
+    fn current_dir(&self) -> std::io::Result<std::path::PathBuf> {
+        self.inner.current_dir()
+    }
+    fn read_dir(&self, dir: &std::path::Path) -> std::io::Result<Vec<DirEntryInfo>> {
+        self.inner.read_dir(dir)
+    }
+    fn metadata(&self, path: &std::path::Path) -> std::io::Result<FileMetadata> {
+        self.inner.metadata(path)
+    }
+    fn read(&self, path: &std::path::Path) -> std::io::Result<Vec<u8>> {
+        if self.inner.current_dir()?.join(path) == self.unreadable {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "permission denied",
+            ));
+        }
+        self.inner.read(path)
+    }
+    fn write(&self, path: &std::path::Path, content: &[u8]) -> std::io::Result<()> {
+        self.inner.write(path, content)
+    }
+}
+
+#[test]
+/// BOM, CRLF, lone CR and invalid UTF-8 don't panic and don't change the categories
+fn test_28_bom_crlf_invalid_utf8() {
+    let categories = classify_lines(
+        &b"\xEF\xBB\xBF//! doc\r\n// comment\rfn a() {}\r\n"[..],
+        FileKind::Src,
+    );
+    assert_eq!(
+        categories,
+        vec![
+            LineCategory::SrcDocComment,
+            LineCategory::SrcComment,
+            LineCategory::SrcCode
+        ]
+    );
+    let categories = classify_lines(&b"/// caf\xE9\nfn a() {}"[..], FileKind::Src);
+    assert_eq!(
+        categories,
+        vec![LineCategory::SrcDocComment, LineCategory::SrcCode]
+    );
+    let (lines, is_utf8) = split_lines_lossy(b"a\r\n\r\nb");
+    assert_eq!(lines, vec!["a", "", "b"]);
+    assert!(is_utf8);
+}
+
+#[test]
+#[cfg(unix)]
+/// a symlink to a parent folder does not cause an infinite recursion
+fn test_29_symlink_loop() {
+    use std::os::unix::fs::symlink;
+    let dir = std::env::temp_dir().join("lmake_lines_of_code_test_29");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src/sub")).unwrap();
+    std::fs::write(dir.join("src/a.rs"), "fn a() {}\n").unwrap();
+    symlink(dir.join("src"), dir.join("src/sub/loop")).unwrap();
+    symlink(dir.join("src/a.rs"), dir.join("src/b.rs")).unwrap();
+
+    let files = traverse_dir_with_exclude_dir(&dir.join("src"), "/*.rs", &[], false).unwrap();
+    assert_eq!(files, vec![dir.join("src/a.rs")]);
+    let mut files = traverse_dir_with_exclude_dir(&dir.join("src"), "/*.rs", &[], true).unwrap();
+    files.sort();
+    assert_eq!(files, vec![dir.join("src/a.rs"), dir.join("src/b.rs")]);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // symlinks are followed by default, follow_symlinks = false skips them
+    assert!(!AppObject::new().no_follow_symlinks);
+    let mut config = Config::default();
+    config
+        .merge(ConfigLayer::from_toml("follow_symlinks = false").unwrap())
+        .unwrap();
+    assert!(config.no_follow_symlinks);
+}
+
+#[test]
+/// the classification rules without files on disk
+fn test_30_count_str() {
+    let src =
+        "//! module doc\n/// fn doc\nfn a() {\n    // comment\n}\n#[cfg(test)]\nmod tests {\n}\n";
+    let v = count_str(src, FileKind::Src);
+    assert_eq!(v.src_doc_comment_lines, 2);
+    assert_eq!(v.src_comment_lines, 1);
+    assert_eq!(v.src_code_lines, 2);
+    // the line #[cfg(test)] is not counted
+    assert_eq!(v.tests_lines, 2);
+    assert_eq!(count_str(src, FileKind::Tests).tests_lines, 8);
+    assert_eq!(count_str(src, FileKind::Examples).examples_lines, 8);
+    assert_eq!(count_str("", FileKind::Src), LinesOfCode::default());
+    assert_eq!(
+        count_reader(std::io::BufReader::new(src.as_bytes()), FileKind::Src),
+        v
+    );
+}
+
+#[test]
+/// the whole pipeline in memory: count, cache and README.md, without touching the disk
+fn test_31_memory_file_system() {
+    let mut app = memory_app(memory_project(&[
+        ("src/lib.rs", "//! doc\nfn a() {}\n// comment\n"),
+        ("src/target/skip.rs", "fn skip() {}\n"),
+        ("tests/t.rs", "#[test]\nfn t() {}\n"),
+        (
+            "README.md",
+            "# x\n[comment]: # (lmake_lines_of_code start)\n[comment]: # (lmake_lines_of_code end)\n",
+        ),
+    ]));
+    // the cache is also written in memory
+    app.no_cache = false;
+    let v = app.workspace_or_project_count_lines();
+    assert_eq!(v, LinesOfCode::new(1, 1, 1, 2, 0));
+    let text_to_include = app.main("http://website");
+    assert!(text_to_include.contains("Lines_in_Rust-1-green"));
+    let readme = app
+        .file_system
+        .read_to_string(std::path::Path::new("README.md"))
+        .unwrap();
+    assert!(readme.contains(&text_to_include));
+    assert!(app
+        .file_system
+        .is_dir(std::path::Path::new("/project/target/lmake_lines_of_code")));
+}
+
+#[test]
+/// the CLI wins over the env vars, the env vars over the config file, the config file over Cargo.toml
+fn test_32_config_precedence() {
+    let file_system = MemoryFileSystem::new("/project")
+        .with_file(
+            "Cargo.toml",
+            "[package]\nname = \"x\"\n[package.metadata.lmake_lines_of_code]\nexclude = [\"src/a.rs\"]\n",
+        )
+        .with_file(
+            ".lmake_lines_of_code.toml",
+            "remote = \"file\"\nbadge_style = \"flat-square\"\nexclude = [\"src/b.rs\"]\nreadme = [\"docs/index.md\"]\n",
+        );
+    let env_vars = vec![
+        ("LMAKE_LOC_REMOTE".to_string(), "env".to_string()),
+        ("LMAKE_LOC_OUTPUT_FORMAT".to_string(), "json".to_string()),
+    ];
+    let cli = ConfigLayer {
+        categories: Some(vec!["code".to_string(), "tests".to_string()]),
+        output_format: Some("markdown".to_string()),
+        ..ConfigLayer::default()
+    };
+    let config = Config::load(&file_system, env_vars.clone(), cli).unwrap();
+    assert_eq!(config.remote_name, "env");
+    assert_eq!(config.output_format, OutputFormat::Markdown);
+    assert_eq!(config.badge_style, "flat-square");
+    assert_eq!(config.exclude, vec!["src/a.rs", "src/b.rs"]);
+    assert_eq!(config.readme_targets, vec!["docs/index.md"]);
+
+    let app = memory_app_with_config(config, file_system);
+    assert!(!app.file_patterns.is_counted("src/b.rs"));
+    let badges =
+        app.to_string_as_shield_badges(&LinesOfCode::new(11, 22, 33, 44, 55), "http://website");
+    assert_eq!(badges, "[![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-11-green.svg?style=flat-square)](http://website)\n[![Lines in tests](https://img.shields.io/badge/Lines_in_tests-44-orange.svg?style=flat-square)](http://website)\n");
+
+    // the project root does not change the current directory of the process
+    let current_dir = std::env::current_dir().unwrap();
+    let config = Config {
+        project_root: "tests".into(),
+        ..Config::default()
+    };
+    let app = AppObject::builder().config(config).build().unwrap();
+    assert_eq!(std::env::current_dir().unwrap(), current_dir);
+    assert!(app
+        .file_system
+        .read_to_string(std::path::Path::new("test_01.rs"))
+        .is_ok());
+    let config = Config {
+        project_root: "no_such_folder".into(),
+        ..Config::default()
+    };
+    assert!(AppObject::builder().config(config).build().is_err());
+
+    let cli = ConfigLayer {
+        categories: Some(vec!["lines".to_string()]),
+        ..ConfigLayer::default()
+    };
+    assert!(Config::load(&MemoryFileSystem::new("/project"), env_vars, cli).is_err());
+    let unknown = vec![("LMAKE_LOC_REMOTES".to_string(), "x".to_string())];
+    // an unknown var is only a warning
+    assert_eq!(
+        ConfigLayer::from_env_vars(unknown).unwrap(),
+        ConfigLayer::default()
+    );
+}
+
+#[test]
+/// workspace defaults from Cargo.toml and the parent folders, with overrides of the members
+fn test_33_config_discovery_and_members() {
+    let file_system = MemoryFileSystem::new("/ws/project")
+        .with_file("/ws/.lmake_lines_of_code.toml", "badge_style = \"plastic\"\nremote = \"upstream\"\n")
+        .with_file("/ws/project/.lmake_lines_of_code.toml", "badge_style = \"flat-square\"\n")
+        .with_file(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\"]\n[workspace.metadata.lmake_lines_of_code]\nexclude = [\"src/skip.rs\"]\n",
+        )
+        .with_file(
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\n[package.metadata.lmake_lines_of_code]\nexclude = [\"src/gen.rs\"]\n",
+        )
+        .with_file("a/src/lib.rs", "fn a() {}\n")
+        .with_file("a/src/gen.rs", "fn gen() {}\n")
+        .with_file("a/src/skip.rs", "fn skip() {}\n")
+        .with_file("a/examples/e.rs", "fn main() {}\n")
+        .with_file("b/Cargo.toml", "[package]\nname = \"b\"\n")
+        .with_file("b/.lmake_lines_of_code.toml", "categories = [\"code\", \"tests\"]\n")
+        .with_file("b/src/lib.rs", "fn b() {}\n")
+        .with_file("b/src/gen.rs", "fn gen() {}\n")
+        .with_file("b/src/skip.rs", "fn skip() {}\n")
+        .with_file("b/examples/e.rs", "fn main() {}\n");
+    let config = Config::load(&file_system, vec![], ConfigLayer::default()).unwrap();
+    assert_eq!(config.badge_style, "flat-square");
+    assert_eq!(config.remote_name, "upstream");
+    assert_eq!(config.exclude, vec!["src/skip.rs"]);
+    assert_eq!(config.members["a"].exclude, vec!["src/gen.rs"]);
+    let app = memory_app_with_config(config, file_system);
+    let v = app.workspace_or_project_count_lines();
+    // a: lib.rs and the example; b: lib.rs and gen.rs, without the example
+    assert_eq!(v, LinesOfCode::new(3, 0, 0, 0, 1));
+}
+
+#[test]
+/// the ratio metrics in the markdown table and the ratio badges with their color thresholds
+fn test_34_ratios() {
+    let v = LinesOfCode::new(200, 50, 30, 80, 10);
+    assert_eq!(v.doc_comments_per_code(), 0.25);
+    assert_eq!(v.comment_density(), 80.0 / 280.0);
+    assert_eq!(v.tests_to_code(), 0.4);
+    assert_eq!(v.examples_to_code(), 0.05);
+    assert_eq!(LinesOfCode::default().tests_to_code(), 0.0);
+    let app = AppObject::new();
+    assert!(app.to_string_as_md_table(&v).contains(
+        "|         0.25          |      0.29       |     0.40      |       0.05       |"
+    ));
+
+    let mut config = Config::default();
+    config
+        .merge(
+            ConfigLayer::from_toml(
+                "ratio_badges = [\"tests_to_code\", \"examples_to_code\"]\n[ratio_thresholds]\ntests_to_code = { yellow = 0.1, green = 0.3 }\n",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    config.categories = vec!["code".to_string()];
+    let app = AppObject::builder().config(config).build().unwrap();
+    let badges = app.to_string_as_shield_badges(&v, "http://website");
+    assert_eq!(badges, "[![Lines in Rust code](https://img.shields.io/badge/Lines_in_Rust-200-green.svg)](http://website)\n[![tests to code](https://img.shields.io/badge/tests_to_code-0.40-green.svg)](http://website)\n[![examples to code](https://img.shields.io/badge/examples_to_code-0.05-yellow.svg)](http://website)\n");
+    assert!(ConfigLayer::from_toml("ratio_badges = [\"speed\"]")
+        .map(|layer| Config::default().merge(layer))
+        .unwrap()
+        .is_err());
+}
+
+#[test]
+/// check-policy evaluates the workspace, member and file rules and rejects invalid rules
+fn test_35_check_policy() {
+    let file_system = memory_project(&[
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        (
+            ".lmake_lines_of_code.toml",
+            "[[policy]]\nmetric = \"tests_to_code\"\nmin = 0.5\n\n[[policy]]\nmetric = \"src_code_lines\"\nmax = 2\nscope = \"file\"\n",
+        ),
+        (
+            "b/Cargo.toml",
+            "[package]\nname = \"b\"\n[[package.metadata.lmake_lines_of_code.policy]]\nmetric = \"src_doc_comment_lines\"\nper = 100\nmin = 10\n",
+        ),
+        ("a/Cargo.toml", "[package]\nname = \"a\"\n"),
+        ("a/src/lib.rs", "/// doc\nfn a() {}\nfn b() {}\nfn c() {}\n"),
+        ("a/tests/t.rs", "fn t() {}\n"),
+        ("b/src/lib.rs", "fn d() {}\n"),
+    ]);
+    let config = Config::load(&file_system, vec![], ConfigLayer::default()).unwrap();
+    assert_eq!(config.policies.len(), 3);
+    assert_eq!(config.policies[2].members, vec!["b"]);
+    assert_eq!(config.policies[2].scope, PolicyScope::Member);
+    let app = memory_app_with_config(config, file_system);
+    let violations = app.check_policy().unwrap();
+    let targets: Vec<&str> = violations.iter().map(|v| v.target.as_str()).collect();
+    assert_eq!(targets, vec!["workspace", "a/src/lib.rs", "b"]);
+    assert_eq!(violations[0].rule, "min tests_to_code = 0.5");
+    assert!(violations_to_md_table(&violations)
+        .contains("| max src_code_lines per file = 2 | a/src/lib.rs | 3 |"));
+    assert!(
+        ConfigLayer::from_toml("[[policy]]\nmetric = \"speed\"\nmin = 1\n")
+            .map(|layer| Config::default().merge(layer))
+            .unwrap()
+            .is_err()
+    ); // the workspace total cannot be limited to some members
+    assert!(ConfigLayer::from_toml(
+        "[[policy]]\nmetric = \"tests_to_code\"\nmin = 1\nmembers = [\"a\"]\n"
+    )
+    .map(|layer| Config::default().merge(layer))
+    .unwrap()
+    .is_err());
+}
+
+#[test]
+/// the baseline report is saved and a drop of lines beyond the allowed percent is a regression
+fn test_36_baseline() {
+    let mut app = memory_app(memory_project(&[
+        ("src/lib.rs", "/// doc\n/// doc\nfn a() {}\n"),
+        ("tests/a.rs", "fn a() {}\nfn b() {}\n"),
+        ("tests/b.rs", "fn c() {}\n"),
+    ]));
+    let report = app.save_baseline("loc-baseline.json").unwrap();
+    assert_eq!(report.total, LinesOfCode::new(1, 2, 0, 3, 0));
+    assert_eq!(report.files[1].path, "tests/a.rs");
+    assert!(app
+        .compare_baseline("loc-baseline.json")
+        .unwrap()
+        .is_empty());
+
+    // one doc comment and one test file less
+    app.file_system
+        .write(std::path::Path::new("src/lib.rs"), b"/// doc\nfn a() {}\n")
+        .unwrap();
+    app.file_system
+        .write(
+            std::path::Path::new("tests/a.rs"),
+            b"fn a() {}\nfn b() {}\nfn x() {}\n",
+        )
+        .unwrap();
+    app.file_system
+        .write(std::path::Path::new("tests/b.rs"), b"")
+        .unwrap();
+    let regressions = app.compare_baseline("loc-baseline.json").unwrap();
+    let found: Vec<(PolicyScope, &str, &str)> = regressions
+        .iter()
+        .map(|r| (r.scope, r.target.as_str(), r.metric.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (PolicyScope::Workspace, "workspace", "src_doc_comment_lines"),
+            (PolicyScope::Member, "", "src_doc_comment_lines"),
+            (PolicyScope::File, "src/lib.rs", "src_doc_comment_lines"),
+            (PolicyScope::File, "tests/b.rs", "tests_lines"),
+        ]
+    );
+    assert!(regressions_to_md_table(&regressions)
+        .contains("| file | tests/b.rs | tests_lines | 1 | 0 | 100.0% |"));
+    app.baseline_max_drop = default_max_drop();
+    app.baseline_max_drop
+        .insert("src_doc_comment_lines".to_string(), 50.0);
+    assert_eq!(app.compare_baseline("loc-baseline.json").unwrap().len(), 1);
+
+    // the user value is added to the default: the code and the doc comments must not drop
+    app.baseline_max_drop = std::collections::BTreeMap::new();
+    app.baseline_max_drop
+        .insert("src_code_lines".to_string(), 0.0);
+    app.file_system
+        .write(std::path::Path::new("src/lib.rs"), b"/// doc\n")
+        .unwrap();
+    let mut metrics: Vec<String> = app
+        .compare_baseline("loc-baseline.json")
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.scope == PolicyScope::Workspace)
+        .map(|r| r.metric)
+        .collect();
+    metrics.sort();
+    assert_eq!(metrics, vec!["src_code_lines", "src_doc_comment_lines"]);
+}
+
+#[test]
+/// the history log gets one line per call and the trend SVG has one panel per category
+fn test_37_history_log_and_trend() {
+    let mut app = memory_app(memory_project(&[("src/lib.rs", "fn a() {}\n")]));
+    app.history_log = "target/loc-history.jsonl".to_string();
+    app.append_history_log(&app.workspace_or_project_count_lines())
+        .unwrap();
+    app.file_system
+        .write(
+            std::path::Path::new("src/lib.rs"),
+            b"fn a() {}\nfn b() {}\n",
+        )
+        .unwrap();
+    app.append_history_log(&app.workspace_or_project_count_lines())
+        .unwrap();
+    // the library calls do not append, only the CLI run appends once
+    app.main("http://website");
+    let entries = app.read_history_log("target/loc-history.jsonl").unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].lines_of_code.src_code_lines, 2);
+    assert!(entries[0].timestamp.ends_with('Z'));
+    let charts = trend_to_ascii_charts(&entries, &app.number_format);
+    assert!(charts.contains("src code: 1 -> 2\n2 | *\n"));
+    assert!(!charts.contains("tests:"));
+    let svg = trend_to_svg(&entries, &app.number_format);
+    assert!(svg.contains("<polyline fill=\"none\" stroke=\"green\""));
+    assert_eq!(svg.matches("<polyline").count(), 1);
+    // every category has its own axis, the small ones are not flat
+    let mut entries = entries;
+    entries[0].lines_of_code.tests_lines = 1000;
+    let svg = trend_to_svg(&entries, &app.number_format);
+    assert_eq!(svg.matches("<polyline").count(), 2);
+    assert!(svg.contains(">2 </text>"));
+    assert!(svg.contains(">1000 </text>"));
+    assert!(parse_history_log("{\"timestamp\": 1}\n").is_err());
+}
+
+#[test]
+/// plain, separated and compact numbers, also escaped in the badge url
+fn test_38_number_format() {
+    let mut number_format = NumberFormat::default();
+    assert_eq!(number_format.format(1234567), "1234567");
+    number_format.style = NumberStyle::Separated;
+    assert_eq!(number_format.format(999), "999");
+    assert_eq!(number_format.format(123456), "123,456");
+    number_format.separator = " ".to_string();
+    assert_eq!(number_format.format(1234567), "1 234 567");
+    assert_eq!(number_format.format_for_badge(1234567), "1_234_567");
+    number_format.style = NumberStyle::Compact;
+    assert_eq!(number_format.format(1000), "1k");
+    assert_eq!(number_format.format(12345), "12.3k");
+    assert_eq!(number_format.format(123456), "123k");
+    assert_eq!(number_format.format(999_950), "1M");
+    assert_eq!(number_format.format(2_500_000_000), "2.5G");
+    number_format.decimals = 2;
+    assert_eq!(number_format.format(1_234_567), "1.23M");
+    assert!("short".parse::<NumberStyle>().is_err());
+
+    let mut config = Config::default();
+    config
+        .merge(
+            ConfigLayer::from_toml("number_format = \"compact\"\nnumber_decimals = 0\n").unwrap(),
+        )
+        .unwrap();
+    let vars = vec![("LMAKE_LOC_THOUSANDS_SEPARATOR".to_string(), ".".to_string())];
+    config
+        .merge(ConfigLayer::from_env_vars(vars).unwrap())
+        .unwrap();
+    assert_eq!(config.number_format.style, NumberStyle::Compact);
+    assert_eq!(config.number_format.separator, ".");
+    assert_eq!(config.number_format.decimals, 0);
+
+    let mut app = AppObject::new();
+    app.number_format.style = NumberStyle::Separated;
+    let v = LinesOfCode::new(123456, 2000, 3, 1500, 4);
+    let badges = app.to_string_as_shield_badges(&v, "http://website");
+    assert!(badges.contains("Lines_in_Rust-123,456-green"));
+    assert!(badges.contains("Lines_in_tests-1,500-orange"));
+    // the separator cannot break the url of the badge or the SVG
+    app.number_format.separator = ")<&".to_string();
+    let badges = app.to_string_as_shield_badges(&v, "http://website");
+    assert!(badges.contains("Lines_in_tests-1%29%3C%26500-orange"));
+    let entries = vec![HistoryLogEntry {
+        timestamp: "2020-08-22T10:00:00Z".to_string(),
+        commit: "".to_string(),
+        lines_of_code: v.clone(),
+    }];
+    let svg = trend_to_svg(&entries, &app.number_format);
+    assert!(svg.contains("123)&lt;&amp;456"));
+    assert!(!svg.contains(")<&"));
+    app.number_format.separator = ",".to_string();
+    assert!(app
+        .to_string_as_md_table(&v)
+        .contains("| 123,456  |    2,000     |"));
+    app.number_format.style = NumberStyle::Compact;
+    let badges = app.to_string_as_shield_badges(&v, "http://website");
+    assert!(badges.contains("Lines_in_Rust-123k-green"));
+    assert!(badges.contains("Lines_in_Doc_comments-2k-blue"));
+    // JSON always has the raw numbers
+    assert!(serde_json::to_string(&v).unwrap().contains("123456"));
+}